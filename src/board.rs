@@ -1,11 +1,26 @@
 use crate::console_log;
-use crate::utils::log;
-use crate::pieces::{ChessMove, MoveType, is_square_attacked, pieces_attacking_square, king_standard_moves};
-use crate::rules::{possible_moves_from_square};
-
-use std::collections::LinkedList;
+use crate::utils::{log, coord_to_rank_file, rank_file_to_coord, square_index_to_rank_file, splitmix64};
+use crate::pieces::{ChessMove, MoveType};
+use crate::rules::pseudo_legal_moves_from_square;
+use crate::zobrist;
+use crate::evaluate::evaluate_centipawns;
+use crate::search::negamax_best_move;
+
+use std::collections::HashMap;
 // use rust_gdb_example::*;
 
+/// Changes in rank and file used to find the squares attacked by each piece.
+const STRAIGHT_DIRECTIONS : [[i32; 2]; 4] = [[1, 0], [-1, 0], [0, 1], [0, -1]];
+const DIAGONAL_DIRECTIONS : [[i32; 2]; 4] = [[1, 1], [1, -1], [-1, 1], [-1, -1]];
+const ALL_DIRECTIONS : [[i32; 2]; 8] = [
+    [1, 0], [-1, 0], [0, 1], [0, -1],
+    [1, 1], [1, -1], [-1, 1], [-1, -1],
+];
+const KNIGHT_JUMPS : [[i32; 2]; 8] = [
+    [2, 1], [2, -1], [-2, 1], [-2, -1],
+    [1, 2], [1, -2], [-1, 2], [-1, -2],
+];
+
 /// The Chess Board. Stores the position of the chess pieces.
 #[derive(Clone, Debug)]
 pub struct Board {
@@ -18,9 +33,31 @@ pub struct Board {
     castle_queen_side_black_avaliable : bool,
     white_king_rank_file : [usize; 2],
     black_king_rank_file : [usize; 2],
+    halfmove_clock : u32,
+    fullmove_number : u32,
     board_history : BoardHistory,
 }
 
+/// The state of the game from the point of view of the side to move.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum GameState {
+    Ongoing,
+    Check,
+    Checkmate,
+    Stalemate,
+    DrawByRepetition,
+    DrawByFiftyMove,
+    DrawByInsufficientMaterial,
+}
+
+/// How a game played by Board::play_random_game ended.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum GameOutcome {
+    Checkmate,
+    Draw,
+    MaxMovesReached,
+}
+
 impl Board {
     pub fn new() -> Board {
         let set_squares: [char; 64] = ['-'; 64];
@@ -35,6 +72,8 @@ impl Board {
             castle_queen_side_black_avaliable: true,
             white_king_rank_file: [1, 5],
             black_king_rank_file: [8, 5],
+            halfmove_clock: 0,
+            fullmove_number: 1,
             board_history: BoardHistory::new(),
         };
 
@@ -53,7 +92,8 @@ impl Board {
         let mut finished_piece_positions = false;
         let mut finished_white_to_move = false;
         let mut finished_castle_availability = false;
-        for ch in fen_string.chars() {
+        let mut remaining_fields = "";
+        for (i, ch) in fen_string.char_indices() {
 
             if !finished_piece_positions {
                 if ch.is_ascii_digit() {
@@ -94,15 +134,109 @@ impl Board {
                 } else if ch == 'q' {
                     self.castle_queen_side_black_avaliable = true;
                 } else if ch == ' ' {
-                    // TODO! implement the enpassant square. Involves 
-                    // reading two characters, so a bit different from 
-                    // the other parts of the fen string.
+                    // The en passant square and move clocks can be more than
+                    // one character long, so parse the rest of the string
+                    // as whitespace separated fields.
+                    remaining_fields = &fen_string[i+1..];
                     finished_castle_availability = true;
                 }
-            }            
+            }
+
+            if finished_castle_availability {
+                break;
+            }
+        }
+
+        // Missing fields default to no en passant square, a halfmove
+        // clock of 0 and the first move of the game.
+        let mut fields = remaining_fields.split_whitespace();
+        self.en_passant_sq = match fields.next() {
+            // "-" indicates there is no en passant square
+            Some(field) => checked_coord_to_rank_file(field).unwrap_or([0, 0]),
+            None => [0, 0],
+        };
+
+        // The en passant square is behind a pawn that has just moved two
+        // squares, so it is on rank 6 with white to move, or rank 3 with
+        // black to move
+        let en_passant_rank = if self.is_white_to_move { 6 } else { 3 };
+        if self.en_passant_sq[0] != en_passant_rank {
+            self.en_passant_sq = [0, 0];
+        }
+        self.halfmove_clock = match fields.next() {
+            Some(field) => field.parse::<u32>().unwrap_or(0),
+            None => 0,
+        };
+        self.fullmove_number = match fields.next() {
+            Some(field) => field.parse::<u32>().unwrap_or(1),
+            None => 1,
+        };
+
+        self.board_history.set_start_position(self.to_fen(), self.zobrist_hash());
+    }
+
+    /// Returns the fen string of the current position. The inverse of
+    /// set_board_from_fen_string.
+    pub fn to_fen(&self) -> String {
+        let mut fen_string = String::new();
+
+        for rank in (1..=8).rev() {
+            let mut num_empty_squares = 0;
+            for file in 1..=8 {
+                let piece = self.get_piece_on_square([rank, file]);
+                if piece == '-' {
+                    num_empty_squares += 1;
+                    continue;
+                }
+
+                if num_empty_squares > 0 {
+                    fen_string.push_str(&num_empty_squares.to_string());
+                    num_empty_squares = 0;
+                }
+                fen_string.push(piece);
+            }
+
+            if num_empty_squares > 0 {
+                fen_string.push_str(&num_empty_squares.to_string());
+            }
+            if rank > 1 {
+                fen_string.push('/');
+            }
+        }
+
+        if self.is_white_to_move {
+            fen_string.push_str(" w ");
+        } else {
+            fen_string.push_str(" b ");
         }
 
-        self.board_history.add_position(self.clone());
+        let mut castle_availability = String::new();
+        if self.castle_king_side_white_avaliable {
+            castle_availability.push('K');
+        }
+        if self.castle_queen_side_white_avaliable {
+            castle_availability.push('Q');
+        }
+        if self.castle_king_side_black_avaliable {
+            castle_availability.push('k');
+        }
+        if self.castle_queen_side_black_avaliable {
+            castle_availability.push('q');
+        }
+        if castle_availability.is_empty() {
+            castle_availability.push('-');
+        }
+        fen_string.push_str(&castle_availability);
+
+        fen_string.push(' ');
+        if self.is_valid_rank_file(self.en_passant_sq) {
+            fen_string.push_str(&rank_file_to_coord(self.en_passant_sq));
+        } else {
+            fen_string.push('-');
+        }
+
+        fen_string.push_str(&format!(" {} {}", self.halfmove_clock, self.fullmove_number));
+        return fen_string;
     }
 
     pub fn is_castle_king_side_avaliable(&self, is_white: bool) -> bool {
@@ -154,9 +288,12 @@ impl Board {
             }
         }
 
+        // Only en passant squares where a capture is possible make a position different
+        let this_en_passant_sq = self.replayable_en_passant_sq();
+        let that_en_passant_sq = that.replayable_en_passant_sq();
         if self.is_white_to_move != that.is_white_to_move ||
-           self.en_passant_sq[0] != that.en_passant_sq[0] ||
-           self.en_passant_sq[1] != that.en_passant_sq[1] ||
+           this_en_passant_sq[0] != that_en_passant_sq[0] ||
+           this_en_passant_sq[1] != that_en_passant_sq[1] ||
            self.castle_king_side_white_avaliable != that.castle_king_side_white_avaliable ||
            self.castle_king_side_black_avaliable != that.castle_king_side_black_avaliable ||
            self.castle_queen_side_white_avaliable != that.castle_queen_side_white_avaliable ||
@@ -168,87 +305,421 @@ impl Board {
         return true;
     }
 
+    /// Returns the en passant square if a pawn of the side to move can legally
+    /// capture en passant, otherwise the invalid square [0, 0]. Positions are only
+    /// different for repetitions if the en passant capture can actually be played.
+    fn replayable_en_passant_sq(&self) -> [usize; 2] {
+        if !self.is_valid_rank_file(self.en_passant_sq) {
+            return [0, 0];
+        }
+
+        // Capturing pawns are beside the pushed pawn, one rank behind the en passant square
+        let pawn = if self.is_white_to_move { 'P' } else { 'p' };
+        let pawn_rank = if self.is_white_to_move { self.en_passant_sq[0] - 1 } else { self.en_passant_sq[0] + 1 };
+        for file in [self.en_passant_sq[1] as i32 - 1, self.en_passant_sq[1] as i32 + 1].iter() {
+            let src = [pawn_rank, *file as usize];
+            if !self.is_valid_rank_file(src) || self.get_piece_on_square(src) != pawn {
+                continue;
+            }
+
+            let can_capture = self.legal_moves_from_square(src).iter()
+                .any(|chess_move| chess_move.move_type == MoveType::EnPassant);
+            if can_capture {
+                return self.en_passant_sq;
+            }
+        }
+
+        return [0, 0];
+    }
+
+    /// Returns the zobrist hash of the current position. Positions that match
+    /// have the same hash. https://www.chessprogramming.org/Zobrist_Hashing
+    pub fn zobrist_hash(&self) -> u64 {
+        let mut hash : u64 = 0;
+        for i in 0..64 {
+            if self.squares[i] != '-' {
+                hash ^= zobrist::piece_square_key(self.squares[i], i);
+            }
+        }
+
+        if !self.is_white_to_move {
+            hash ^= zobrist::black_to_move_key();
+        }
+
+        let castle_rights = [self.castle_king_side_white_avaliable,
+                             self.castle_queen_side_white_avaliable,
+                             self.castle_king_side_black_avaliable,
+                             self.castle_queen_side_black_avaliable];
+        for i in 0..4 {
+            if castle_rights[i] {
+                hash ^= zobrist::castle_key(i);
+            }
+        }
+
+        let en_passant_sq = self.replayable_en_passant_sq();
+        if self.is_valid_rank_file(en_passant_sq) {
+            hash ^= zobrist::en_passant_file_key(en_passant_sq[1]);
+        }
+
+        return hash;
+    }
+
     pub fn is_check(&self) -> bool {
         let king_rank_file = self.get_king_rank_file();
         if !self.is_valid_rank_file(king_rank_file) {
             return false;
         }
         let is_white = self.white_to_move();
-        return is_square_attacked(&self, king_rank_file, !is_white);
+        let attacked_squares = self.attack_map(!is_white);
+        return attacked_squares[self.on_board_square_index(king_rank_file)];
     }
 
-    pub fn is_checkmate(&self) -> bool {
-        // Check if the king is in check
-        let king_rank_file = self.get_king_rank_file();
-        if !self.is_valid_rank_file(king_rank_file) {
-            return false;
+    /// Returns which squares are attacked by the pieces of one colour, indexed
+    /// the same as the squares (a8, b8 ... g1, h1). Squares occupied by either
+    /// colour can be attacked, and sliding pieces are stopped by the first
+    /// piece in their way. Computed in one pass over the board, so it is
+    /// cheaper than calling is_square_attacked for several squares.
+    pub fn attack_map(&self, by_white: bool) -> [bool; 64] {
+        let mut attacked_squares = [false; 64];
+        let white_pawn_captures : [[i32; 2]; 2] = [[1, -1], [1, 1]];
+        let black_pawn_captures : [[i32; 2]; 2] = [[-1, -1], [-1, 1]];
+
+        for square_inx in 0..64 {
+            let piece = self.squares[square_inx];
+            if piece == '-' || piece.is_uppercase() != by_white {
+                continue;
+            }
+
+            let (directions, is_slider) : (&[[i32; 2]], bool) = match piece.to_ascii_uppercase() {
+                'P' if by_white => (&white_pawn_captures, false),
+                'P' => (&black_pawn_captures, false),
+                'N' => (&KNIGHT_JUMPS, false),
+                'B' => (&DIAGONAL_DIRECTIONS, true),
+                'R' => (&STRAIGHT_DIRECTIONS, true),
+                'Q' => (&ALL_DIRECTIONS, true),
+                'K' => (&ALL_DIRECTIONS, false),
+                _ => continue,
+            };
+
+            let src = square_index_to_rank_file(square_inx);
+            for direction in directions {
+                let mut rank = src[0] as i32 + direction[0];
+                let mut file = src[1] as i32 + direction[1];
+                while (1..=8).contains(&rank) && (1..=8).contains(&file) {
+                    let target_inx = self.on_board_square_index([rank as usize, file as usize]);
+                    attacked_squares[target_inx] = true;
+
+                    if !is_slider || self.squares[target_inx] != '-' {
+                        break;
+                    }
+                    rank += direction[0];
+                    file += direction[1];
+                }
+            }
         }
-        let is_white = self.white_to_move();
-        if !is_square_attacked(&self, king_rank_file, !is_white) {
+
+        return attacked_squares;
+    }
+
+    /// Returns false if the position could not occur in a game: either side
+    /// does not have exactly one king, a pawn is on the first or last rank,
+    /// or the side that just moved has left its king in check.
+    pub fn is_position_legal(&self) -> bool {
+        let mut num_white_kings = 0;
+        let mut num_black_kings = 0;
+        for rank in 1..=8 {
+            for file in 1..=8 {
+                let piece = self.get_piece_on_square([rank, file]);
+                match piece {
+                    'K' => num_white_kings += 1,
+                    'k' => num_black_kings += 1,
+                    'P' | 'p' => {
+                        if rank == 1 || rank == 8 {
+                            return false;
+                        }
+                    },
+                    _ => {},
+                }
+            }
+        }
+
+        if num_white_kings != 1 || num_black_kings != 1 {
             return false;
         }
 
-        // Check if the king can move any where. need to modify the board for this
-        let possible_moves = king_standard_moves(&self, king_rank_file, is_white, false);
-        let mut board_copy = self.clone();
-        board_copy.clear_square(king_rank_file);
-        for possible_move in possible_moves {
-            let dest = possible_move.dest;
-            if !is_square_attacked(&board_copy, dest, !is_white) {            
-                return false;
+        let is_white = self.is_white_to_move;
+        let other_king_rank_file = if is_white {
+            self.black_king_rank_file
+        } else {
+            self.white_king_rank_file
+        };
+        let attacked_squares = self.attack_map(is_white);
+        return !attacked_squares[self.on_board_square_index(other_king_rank_file)];
+    }
+
+    /// The side to move is in check and has no legal moves.
+    pub fn is_checkmate(&self) -> bool {
+        return self.game_state() == GameState::Checkmate;
+    }
+
+    /// The game is drawn by stalemate, three fold repetition, the fifty
+    /// move rule or insufficient material.
+    pub fn is_draw(&self) -> bool {
+        return match self.game_state() {
+            GameState::Stalemate |
+            GameState::DrawByRepetition |
+            GameState::DrawByFiftyMove |
+            GameState::DrawByInsufficientMaterial => true,
+            _ => false,
+        };
+    }
+
+    /// Returns the state of the game for the side to move. Running out of
+    /// legal moves ends the game first, so checkmate on the move that would
+    /// otherwise draw by the fifty move rule still counts as checkmate.
+    pub fn game_state(&self) -> GameState {
+        let is_check = self.is_check();
+
+        if !self.has_legal_moves() {
+            if is_check {
+                return GameState::Checkmate;
             }
+            return GameState::Stalemate;
         }
 
-        // Check if the attacking piece can be captured, or if the attack can be
-        // intercepted (by moving a friendly piece in front of the attack)
-        let attacking_moves = pieces_attacking_square(&self, king_rank_file, !is_white);
-        for attacking_move in attacking_moves {
-            let moves_to_capture_attacker = pieces_attacking_square(&self, attacking_move.src, is_white);
-            if moves_to_capture_attacker.len() > 0 {
+        if let Some(draw) = self.draw_by_rule() {
+            return draw;
+        }
 
-                // If this is the king capturing it's own attacker, make sure the king
-                // did not move into check.
-                if !(moves_to_capture_attacker.len() == 1 && 
-                   moves_to_capture_attacker[0].piece.to_ascii_uppercase() == 'K' &&
-                   is_square_attacked(&self, moves_to_capture_attacker[0].dest, !is_white) ) {
+        if is_check {
+            return GameState::Check;
+        }
+        return GameState::Ongoing;
+    }
 
-                    return false;
-                }
+    /// Returns the draws which do not depend on the legal moves: three fold
+    /// repetition, the fifty move rule and insufficient material. Used by
+    /// the search, which already has the legal moves to check for mate and
+    /// stalemate.
+    pub fn draw_by_rule(&self) -> Option<GameState> {
+        if self.board_history.has_threefold_repetition_occurred() {
+            return Some(GameState::DrawByRepetition);
+        }
+
+        if self.halfmove_clock >= 100 {
+            return Some(GameState::DrawByFiftyMove);
+        }
+
+        if self.has_insufficient_material() {
+            return Some(GameState::DrawByInsufficientMaterial);
+        }
+
+        return None;
+    }
+
+    /// Returns true if the side to move has at least one legal move. Stops
+    /// at the first legal move found.
+    fn has_legal_moves(&self) -> bool {
+        for occupied_square in self.all_occupied_squares(self.is_white_to_move) {
+            if !self.legal_moves_from_square(occupied_square).is_empty() {
+                return true;
             }
+        }
+        return false;
+    }
 
-            if can_attack_be_intercepted(&self, attacking_move) {
-                return false;
+    /// Returns the same position with the colours swapped: the ranks are
+    /// mirrored top to bottom, white pieces become black pieces and the
+    /// other side is to move. The castle rights, kings and en passant square
+    /// are swapped to match. The move history is not kept.
+    pub fn mirrored(&self) -> Board {
+        let mut mirrored_board = self.clone();
+        for rank in 1..=8 {
+            for file in 1..=8 {
+                let piece = self.get_piece_on_square([rank, file]);
+                let swapped_piece = if piece.is_uppercase() {
+                    piece.to_ascii_lowercase()
+                } else {
+                    piece.to_ascii_uppercase()
+                };
+                let mirrored_inx = mirrored_board.on_board_square_index([9 - rank, file]);
+                mirrored_board.squares[mirrored_inx] = swapped_piece;
             }
         }
 
-        return true;
+        mirrored_board.is_white_to_move = !self.is_white_to_move;
+        mirrored_board.castle_king_side_white_avaliable = self.castle_king_side_black_avaliable;
+        mirrored_board.castle_king_side_black_avaliable = self.castle_king_side_white_avaliable;
+        mirrored_board.castle_queen_side_white_avaliable = self.castle_queen_side_black_avaliable;
+        mirrored_board.castle_queen_side_black_avaliable = self.castle_queen_side_white_avaliable;
+        mirrored_board.white_king_rank_file = mirrored_rank_file(self.black_king_rank_file);
+        mirrored_board.black_king_rank_file = mirrored_rank_file(self.white_king_rank_file);
+        mirrored_board.en_passant_sq = mirrored_rank_file(self.en_passant_sq);
+
+        mirrored_board.board_history.clear();
+        let fen_string = mirrored_board.to_fen();
+        let hash = mirrored_board.zobrist_hash();
+        mirrored_board.board_history.set_start_position(fen_string, hash);
+
+        return mirrored_board;
     }
 
-    pub fn is_draw(&self) -> bool {
+    /// Returns the static evaluation of the position in centipawns, from
+    /// white's perspective.
+    pub fn evaluate(&self) -> i32 {
+        return evaluate_centipawns(&self);
+    }
 
-        // Check for draw by three fold repetition
-        if self.board_history.has_threefold_repetition_occurred() {
-            return true;
-        }
+    /// Searches to the given depth and returns the best move for the side to
+    /// move, or None if there are no legal moves.
+    pub fn search_best_move(&self, depth: u32) -> Option<ChessMove> {
+        return negamax_best_move(&self, depth);
+    }
+
+    /// Plays random legal moves until the game ends or max_moves moves (by
+    /// either side) have been played. The same seed always plays the same
+    /// game. Used for fuzzing the move generation and game end checks.
+    pub fn play_random_game(&mut self, rng_seed: u64, max_moves: u32) -> GameOutcome {
+        let mut rng_state = rng_seed;
+        let mut moves_played = 0;
+        loop {
+            if self.is_checkmate() {
+                return GameOutcome::Checkmate;
+            }
+            if self.is_draw() {
+                return GameOutcome::Draw;
+            }
+            if moves_played >= max_moves {
+                return GameOutcome::MaxMovesReached;
+            }
 
+            let legal_moves = self.get_legal_moves();
+            let (next_rng_state, random) = splitmix64(rng_state);
+            rng_state = next_rng_state;
+            let move_inx = (random % legal_moves.len() as u64) as usize;
+            self.make_move(legal_moves[move_inx]);
+            moves_played += 1;
+        }
+    }
 
-        // Check for draw by stalemate
+    /// Returns every legal move for the side to move.
+    pub fn get_legal_moves(&self) -> Vec<ChessMove> {
+        let mut legal_moves : Vec<ChessMove> = vec![];
         let occupied_squares = self.all_occupied_squares(self.is_white_to_move);
         for occupied_square in occupied_squares {
+            legal_moves.append(&mut self.legal_moves_from_square(occupied_square));
+        }
 
-            // If there are any moves in the current position, not a stalemate
-            if possible_moves_from_square(&self, occupied_square).len() > 0 {
-                return false;
+        return legal_moves;
+    }
+
+    /// Returns the legal moves which capture a piece, including en passant
+    /// captures and promotions that capture. Used by the quiescence search.
+    pub fn capture_moves(&self) -> Vec<ChessMove> {
+        let mut capture_moves : Vec<ChessMove> = vec![];
+        for legal_move in self.get_legal_moves() {
+            let is_capture = if self.is_white_to_move {
+                self.is_occupied_by_black(legal_move.dest)
+            } else {
+                self.is_occupied_by_white(legal_move.dest)
+            };
+
+            if is_capture || legal_move.move_type == MoveType::EnPassant {
+                capture_moves.push(legal_move);
             }
         }
 
-        return true;
+        return capture_moves;
     }
 
-    pub fn make_move(&mut self, chess_move: ChessMove) {
+    /// Returns true if the move is one of the legal moves for the side to
+    /// move, i.e. the same piece, squares and move type, and it does not
+    /// leave the king in check.
+    pub fn is_legal_move(&self, chess_move: &ChessMove) -> bool {
+        let piece = self.get_piece_on_square(chess_move.src);
+        if piece == '-' || piece.is_uppercase() != self.is_white_to_move {
+            return false;
+        }
+
+        for legal_move in self.legal_moves_from_square(chess_move.src) {
+            if legal_move.is_the_same_as(chess_move) {
+                return true;
+            }
+        }
+
+        return false;
+    }
+
+    /// Returns true if neither side has enough material to checkmate: king vs king,
+    /// king and minor piece vs king, or king and bishop vs king and bishop with
+    /// both bishops on the same colour square.
+    pub fn has_insufficient_material(&self) -> bool {
+        let mut white_minor_pieces = 0;
+        let mut black_minor_pieces = 0;
+        let mut bishop_square_colours : Vec<usize> = vec![];
+        for i in 0..64 {
+            match self.squares[i] {
+                'P' | 'p' | 'R' | 'r' | 'Q' | 'q' => return false,
+                'N' => white_minor_pieces += 1,
+                'n' => black_minor_pieces += 1,
+                'B' => {
+                    white_minor_pieces += 1;
+                    bishop_square_colours.push((i / 8 + i % 8) % 2);
+                },
+                'b' => {
+                    black_minor_pieces += 1;
+                    bishop_square_colours.push((i / 8 + i % 8) % 2);
+                },
+                _ => {},
+            }
+        }
+
+        if white_minor_pieces + black_minor_pieces <= 1 {
+            return true;
+        }
+
+        // Only a bishop each, on the same colour square
+        return white_minor_pieces == 1 && black_minor_pieces == 1 &&
+               bishop_square_colours.len() == 2 &&
+               bishop_square_colours[0] == bishop_square_colours[1];
+    }
+
+    /// Makes the move and returns the captured piece, or '-' if no piece was
+    /// captured. The captured piece is needed to unmake the move.
+    pub fn make_move(&mut self, chess_move: ChessMove) -> char {
         if (self.is_white_to_move && !chess_move.piece.is_uppercase()) ||
            (!self.is_white_to_move && chess_move.piece.is_uppercase()) {
-            return;
+            return '-';
+        }
+
+        if !self.is_valid_rank_file(chess_move.src) || !self.is_valid_rank_file(chess_move.dest) {
+            console_log!("Board::make_move: Move {:?} is off the board", chess_move);
+            return '-';
+        }
+
+        // En passant captures are only possible immediately after the double pawn push
+        if chess_move.move_type == MoveType::EnPassant && chess_move.dest != self.en_passant_sq {
+            console_log!("Board::make_move: En passant capture does not target the en passant square");
+            return '-';
+        }
+
+        let undo = MoveUndo {
+            en_passant_sq: self.en_passant_sq,
+            castle_king_side_white_avaliable: self.castle_king_side_white_avaliable,
+            castle_king_side_black_avaliable: self.castle_king_side_black_avaliable,
+            castle_queen_side_white_avaliable: self.castle_queen_side_white_avaliable,
+            castle_queen_side_black_avaliable: self.castle_queen_side_black_avaliable,
+            halfmove_clock: self.halfmove_clock,
+            fullmove_number: self.fullmove_number,
+        };
+
+        // The halfmove clock is reset by any pawn move or capture, which
+        // includes en passant and promotions.
+        let mut captured_piece = self.get_piece_on_square(chess_move.dest);
+        if captured_piece != '-' || chess_move.piece.to_ascii_uppercase() == 'P' {
+            self.halfmove_clock = 0;
+        } else {
+            self.halfmove_clock += 1;
         }
         
         self.move_piece(chess_move.src, chess_move.dest);
@@ -274,13 +745,18 @@ impl Board {
                     self.castle_queen_side_black_avaliable = false;
                 }
 
+                // Check if a rook has moved or been captured. Each corner
+                // must be checked independently.
                 if self.get_piece_on_square([1, 1]) != 'R' {
                     self.castle_queen_side_white_avaliable = false;
-                } else if self.get_piece_on_square([1, 8]) != 'R' {
+                }
+                if self.get_piece_on_square([1, 8]) != 'R' {
                     self.castle_king_side_white_avaliable = false;
-                } else if self.get_piece_on_square([8, 1]) != 'r' {
+                }
+                if self.get_piece_on_square([8, 1]) != 'r' {
                     self.castle_queen_side_black_avaliable = false;
-                } else if self.get_piece_on_square([8, 8]) != 'r' {
+                }
+                if self.get_piece_on_square([8, 8]) != 'r' {
                     self.castle_king_side_black_avaliable = false;
                 }
             } , 
@@ -307,17 +783,13 @@ impl Board {
                     self.castle_king_side_white_avaliable = false;
                 } else {
                     self.castle_queen_side_black_avaliable = false;
-                    self.castle_king_side_white_avaliable = false;
+                    self.castle_king_side_black_avaliable = false;
                 }
             },
             MoveType::EnPassant => {
-                if chess_move.piece == 'P' {
-                    self.clear_square([chess_move.dest[0] - 1, chess_move.dest[1]]);
-                } else if chess_move.piece == 'p' {
-                    self.clear_square([chess_move.dest[0] + 1, chess_move.dest[1]]);
-                } else {
-                    assert!(false);
-                }
+                let captured_pawn_sq = en_passant_captured_pawn_sq(&chess_move);
+                captured_piece = self.get_piece_on_square(captured_pawn_sq);
+                self.clear_square(captured_pawn_sq);
             },
             MoveType::PromoteToQueen => {
                 let promoted_piece : char;
@@ -326,7 +798,7 @@ impl Board {
                 } else {
                     promoted_piece = 'q';
                 }
-                self.squares[self.square_index(chess_move.dest)] = promoted_piece;
+                self.squares[self.on_board_square_index(chess_move.dest)] = promoted_piece;
             },
             MoveType::PromoteToRook => {
                 let promoted_piece : char;
@@ -335,7 +807,7 @@ impl Board {
                 } else {
                     promoted_piece = 'r';
                 }
-                self.squares[self.square_index(chess_move.dest)] = promoted_piece;
+                self.squares[self.on_board_square_index(chess_move.dest)] = promoted_piece;
             },
             MoveType::PromoteToBishop => {
                 let promoted_piece : char;
@@ -344,7 +816,7 @@ impl Board {
                 } else {
                     promoted_piece = 'b';
                 }
-                self.squares[self.square_index(chess_move.dest)] = promoted_piece;
+                self.squares[self.on_board_square_index(chess_move.dest)] = promoted_piece;
             },
             MoveType::PromoteToKnight => {
                 let promoted_piece : char;
@@ -353,7 +825,7 @@ impl Board {
                 } else {
                     promoted_piece = 'n';
                 }
-                self.squares[self.square_index(chess_move.dest)] = promoted_piece;
+                self.squares[self.on_board_square_index(chess_move.dest)] = promoted_piece;
             },
             MoveType::Invalid => {
                 console_log!("Board::make_move: Invalid mode type");
@@ -361,108 +833,538 @@ impl Board {
             }
         }
 
+        // The fullmove number starts at 1 and increases after black's move
+        if !self.is_white_to_move {
+            self.fullmove_number += 1;
+        }
+
         self.is_white_to_move = !self.is_white_to_move;
-        self.board_history.add_position(self.clone());
+        self.board_history.add_move(self.zobrist_hash(), chess_move, undo);
+        return captured_piece;
     }
 
-    /// Returns the piece on the squar, specified by the square index
-    pub fn get_piece_by_square_index(&self, square_inx : usize) -> char {
-        assert!(square_inx < 64);
-        return self.squares[square_inx];
-    }
-    
-    /// Returns the piece on the square specified by a rank and file. 
-    pub fn get_piece_on_square(&self, rank_file: [usize; 2]) -> char {
-        return self.squares[self.square_index(rank_file)];
+    /// Makes each of the moves in order. If a move is not legal, the moves
+    /// already made are unmade and the index of the illegal move is
+    /// returned, leaving the board as it was before the call.
+    pub fn make_moves(&mut self, moves: &[ChessMove]) -> Result<(), usize> {
+        let mut captured_pieces : Vec<char> = vec![];
+        for (i, chess_move) in moves.iter().enumerate() {
+            if !self.is_legal_move(chess_move) {
+                for j in (0..i).rev() {
+                    self.unmake_move(moves[j], captured_pieces[j]);
+                }
+                return Err(i);
+            }
+            captured_pieces.push(self.make_move(*chess_move));
+        }
+
+        return Ok(());
     }
 
-    /// Render the board to the console. Only used when running the tests.
-    pub fn render(&self) {
-        for rank in (1..=8).rev() {
-            for file in 1..=8 {
-                eprint!(" {} ", self.get_piece_on_square([rank, file]));
+    /// Takes back the last move made by make_move. The captured piece is
+    /// the piece returned by make_move. Nothing is unmade if the move is not
+    /// the last move made, for example when make_move refused the move.
+    pub fn unmake_move(&mut self, chess_move: ChessMove, captured: char) {
+        match self.board_history.last_move() {
+            Some(last_move) if last_move.is_the_same_as(&chess_move) => {},
+            _ => {
+                console_log!("Board::unmake_move: {:?} is not the last move made", chess_move);
+                return;
             }
-            eprintln!("");
         }
-    }
 
-    pub fn white_to_move(&self) -> bool {
-        return self.is_white_to_move;
-    }
+        let undo = match self.board_history.remove_last_move() {
+            Some(undo) => undo,
+            None => {
+                console_log!("Board::unmake_move: No move to unmake");
+                return;
+            }
+        };
 
-    pub fn set_is_white_to_move(&mut self, is_white_to_move: bool) {
-        self.is_white_to_move = is_white_to_move;
-    }
+        self.is_white_to_move = !self.is_white_to_move;
+        self.en_passant_sq = undo.en_passant_sq;
+        self.castle_king_side_white_avaliable = undo.castle_king_side_white_avaliable;
+        self.castle_king_side_black_avaliable = undo.castle_king_side_black_avaliable;
+        self.castle_queen_side_white_avaliable = undo.castle_queen_side_white_avaliable;
+        self.castle_queen_side_black_avaliable = undo.castle_queen_side_black_avaliable;
+        self.halfmove_clock = undo.halfmove_clock;
+        self.fullmove_number = undo.fullmove_number;
 
-    /// Methods for checking if a square is free
-    pub fn is_occupied(&self, rank_file: [usize; 2]) -> bool {
-        let piece = self.get_piece_on_square(rank_file);
-        return piece != '-';
+        match chess_move.move_type {
+            MoveType::Standard => {
+                self.move_piece(chess_move.dest, chess_move.src);
+                self.set_piece(captured, chess_move.dest);
+            },
+            MoveType::CastleKingSide => {
+                self.move_piece(chess_move.dest, chess_move.src);
+                self.move_piece([chess_move.src[0], 6], [chess_move.src[0], 8]);
+            },
+            MoveType::CastleQueenSide => {
+                self.move_piece(chess_move.dest, chess_move.src);
+                self.move_piece([chess_move.src[0], 4], [chess_move.src[0], 1]);
+            },
+            MoveType::EnPassant => {
+                self.move_piece(chess_move.dest, chess_move.src);
+                self.set_piece(captured, en_passant_captured_pawn_sq(&chess_move));
+            },
+            MoveType::PromoteToQueen | MoveType::PromoteToRook |
+            MoveType::PromoteToBishop | MoveType::PromoteToKnight => {
+                self.set_piece(chess_move.piece, chess_move.src);
+                self.set_piece(captured, chess_move.dest);
+            },
+            MoveType::Invalid => {
+                console_log!("Board::unmake_move: Invalid mode type");
+                panic!();
+            }
+        }
     }
 
-    pub fn is_occupied_by_white(&self, rank_file: [usize; 2]) -> bool {
-        if !self.is_occupied(rank_file) {
-            return false;
-        }
+    /// Returns the legal moves for the piece on the square. Moves which leave
+    /// the moving side's king in check are removed.
+    pub fn legal_moves_from_square(&self, rank_file: [usize; 2]) -> Vec<ChessMove> {
         let is_white = self.get_piece_on_square(rank_file).is_uppercase();
-        return is_white;
-    }
+        let own_king_rank_file = if is_white {
+            self.white_king_rank_file
+        } else {
+            self.black_king_rank_file
+        };
 
-    pub fn is_occupied_by_black(&self, rank_file: [usize; 2]) -> bool {
-        if !self.is_occupied(rank_file) {
-            return false;
+        // A pinned piece can only move along the ray between its king and
+        // the pinning piece
+        let mut pin_direction : Option<[i32; 2]> = None;
+        for (pinned_sq, direction) in self.pinned_pieces(is_white) {
+            if pinned_sq == rank_file {
+                pin_direction = Some(direction);
+            }
         }
-        let is_black = !self.get_piece_on_square(rank_file).is_uppercase();
-        return is_black;
-    }
 
-    pub fn is_occupied_by_king(&self, rank_file: [usize; 2]) -> bool {
-        let piece = self.get_piece_on_square(rank_file);
-        return piece == 'k' || piece == 'K';
-    }
+        let is_in_check = self.is_valid_rank_file(own_king_rank_file) &&
+            self.attack_map(!is_white)[self.on_board_square_index(own_king_rank_file)];
 
-    pub fn get_king_rank_file(&self) -> [usize; 2] {
+        let mut legal_moves : Vec<ChessMove> = vec![];
+        for chess_move in pseudo_legal_moves_from_square(&self, rank_file) {
+            if let Some(direction) = pin_direction {
+                if !is_along_direction(own_king_rank_file, direction, chess_move.dest) {
+                    continue;
+                }
+            }
 
-        if self.is_white_to_move {
-            return self.white_king_rank_file;
-        } 
+            // Once pins are accounted for, only king moves, en passant captures
+            // and moves out of check can leave the king in check
+            let is_king_move = chess_move.piece.to_ascii_uppercase() == 'K';
+            if !is_in_check && !is_king_move && chess_move.move_type != MoveType::EnPassant {
+                legal_moves.push(chess_move);
+                continue;
+            }
 
-        return self.black_king_rank_file;
-    }
+            let mut board_after_move = self.clone();
+            board_after_move.set_is_white_to_move(is_white);
+            board_after_move.make_move(chess_move);
+
+            let king_rank_file = if is_white {
+                board_after_move.white_king_rank_file
+            } else {
+                board_after_move.black_king_rank_file
+            };
+            if !self.is_valid_rank_file(king_rank_file) ||
+               !board_after_move.attack_map(!is_white)[self.on_board_square_index(king_rank_file)] {
+                legal_moves.push(chess_move);
+            }
+        }
 
-    pub fn is_valid_rank_file(&self, rank_file: [usize; 2]) -> bool {
-        return !(rank_file[0] > 8 || rank_file[0] < 1 || rank_file[1] > 8 || rank_file[1] < 1);
+        return legal_moves;
     }
 
-    /// Change the value of a square without making a move.
-    pub fn clear_square(&mut self, rank_file: [usize; 2]) {
-        self.squares[self.square_index(rank_file)] = '-';
-    }
+    /// Returns the pieces of the given colour that are pinned to their king,
+    /// each paired with the direction of the pin, as the change in rank and
+    /// file from the king towards the pinned piece. The pinned piece can only
+    /// move along that ray.
+    pub fn pinned_pieces(&self, is_white: bool) -> Vec<([usize; 2], [i32; 2])> {
+        let king_rank_file = if is_white {
+            self.white_king_rank_file
+        } else {
+            self.black_king_rank_file
+        };
 
-    /// Returns all the squares occupied by pieces of the specified
-    /// colour.
-    pub fn all_occupied_squares(&self, find_occupied_by_white: bool) -> Vec<[usize; 2]> {
-        let mut occupied_squares : Vec<[usize; 2]> = vec![];
-        for rank in (1 as usize)..(9 as usize) {
-            for file in (1 as usize)..(9 as usize) {
-                if ( find_occupied_by_white && self.is_occupied_by_white([rank, file]) ) ||
-                   (!find_occupied_by_white && self.is_occupied_by_black([rank, file]) ) {
-                    occupied_squares.push([rank, file]);
+        let mut pins : Vec<([usize; 2], [i32; 2])> = vec![];
+        if !self.is_valid_rank_file(king_rank_file) {
+            return pins;
+        }
+
+        for direction in ALL_DIRECTIONS {
+            let is_diagonal = direction[0] != 0 && direction[1] != 0;
+            let mut friendly_blocker : Option<[usize; 2]> = None;
+            let mut rank = king_rank_file[0] as i32 + direction[0];
+            let mut file = king_rank_file[1] as i32 + direction[1];
+
+            while (1..=8).contains(&rank) && (1..=8).contains(&file) {
+                let square = [rank as usize, file as usize];
+                let piece = self.get_piece_on_square(square);
+
+                if piece != '-' {
+                    if piece.is_uppercase() == is_white {
+                        if friendly_blocker.is_some() {
+                            // Two friendly pieces in the way, so no pin
+                            break;
+                        }
+                        friendly_blocker = Some(square);
+                    } else {
+                        let piece_type = piece.to_ascii_uppercase();
+                        let slides_along_ray = piece_type == 'Q' ||
+                            (is_diagonal && piece_type == 'B') ||
+                            (!is_diagonal && piece_type == 'R');
+                        if let Some(pinned_sq) = friendly_blocker {
+                            if slides_along_ray {
+                                pins.push((pinned_sq, direction));
+                            }
+                        }
+                        break;
+                    }
                 }
+
+                rank += direction[0];
+                file += direction[1];
             }
         }
 
-        return occupied_squares;
+        return pins;
     }
 
-    pub fn clear_history(&mut self) {
+    /// Performance test. Counts the number of leaf nodes in the tree of legal
+    /// moves to the given depth. Used to validate the move generation against
+    /// known results: https://www.chessprogramming.org/Perft_Results
+    pub fn perft(&mut self, depth: u32) -> u64 {
+        if depth == 0 {
+            return 1;
+        }
+
+        let mut num_leaves : u64 = 0;
+        let occupied_squares = self.all_occupied_squares(self.is_white_to_move);
+        for occupied_square in occupied_squares {
+            for chess_move in self.legal_moves_from_square(occupied_square) {
+                let captured_piece = self.make_move(chess_move);
+                num_leaves += self.perft(depth - 1);
+                self.unmake_move(chess_move, captured_piece);
+            }
+        }
+
+        return num_leaves;
+    }
+
+    /// Returns the move in standard algebraic notation, such as Nf3, exd5,
+    /// O-O, e8=Q or Rad1. See https://en.wikipedia.org/wiki/Algebraic_notation_(chess)
+    pub fn move_to_san(&self, chess_move: &ChessMove) -> String {
+        let mut san = String::new();
+        let piece_type = chess_move.piece.to_ascii_uppercase();
+
+        match chess_move.move_type {
+            MoveType::CastleKingSide => san.push_str("O-O"),
+            MoveType::CastleQueenSide => san.push_str("O-O-O"),
+            _ => {
+                let is_capture = self.is_occupied(chess_move.dest) || 
+                                 chess_move.move_type == MoveType::EnPassant;
+                let src_coord = rank_file_to_coord(chess_move.src);
+
+                if piece_type == 'P' {
+                    if is_capture {
+                        san.push_str(&src_coord[..1]);
+                    }
+                } else {
+                    san.push(piece_type);
+                    san.push_str(&self.san_disambiguation(chess_move));
+                }
+
+                if is_capture {
+                    san.push('x');
+                }
+                san.push_str(&rank_file_to_coord(chess_move.dest));
+
+                match chess_move.move_type {
+                    MoveType::PromoteToQueen => san.push_str("=Q"),
+                    MoveType::PromoteToRook => san.push_str("=R"),
+                    MoveType::PromoteToBishop => san.push_str("=B"),
+                    MoveType::PromoteToKnight => san.push_str("=N"),
+                    _ => {},
+                }
+            },
+        }
+
+        let mut board_after_move = self.clone();
+        board_after_move.make_move(*chess_move);
+        if board_after_move.is_checkmate() {
+            san.push('#');
+        } else if board_after_move.is_check() {
+            san.push('+');
+        }
+
+        return san;
+    }
+
+    /// Returns the file, rank, or both, of the moving piece when another piece
+    /// of the same type can also move to the destination square.
+    fn san_disambiguation(&self, chess_move: &ChessMove) -> String {
+        let mut is_ambiguous = false;
+        let mut is_file_shared = false;
+        let mut is_rank_shared = false;
+
+        let occupied_squares = self.all_occupied_squares(chess_move.is_white_piece());
+        for occupied_square in occupied_squares {
+            if occupied_square == chess_move.src ||
+               self.get_piece_on_square(occupied_square) != chess_move.piece {
+                continue;
+            }
+
+            let can_reach_dest = self.legal_moves_from_square(occupied_square).iter()
+                .any(|possible_move| possible_move.dest == chess_move.dest);
+            if can_reach_dest {
+                is_ambiguous = true;
+                is_file_shared |= occupied_square[1] == chess_move.src[1];
+                is_rank_shared |= occupied_square[0] == chess_move.src[0];
+            }
+        }
+
+        let src_coord = rank_file_to_coord(chess_move.src);
+        if !is_ambiguous {
+            return String::new();
+        } else if !is_file_shared {
+            return String::from(&src_coord[..1]);
+        } else if !is_rank_shared {
+            return String::from(&src_coord[1..]);
+        }
+        return src_coord;
+    }
+
+    /// Parses a move in long algebraic notation, such as e2e4 or e7e8q, or
+    /// a castle, O-O or O-O-O. Returns None if the string is malformed or
+    /// does not move a piece belonging to the side to move.
+    pub fn parse_move(&self, input: &str) -> Option<ChessMove> {
+        let input = input.trim();
+        let back_rank = if self.is_white_to_move { 1 } else { 8 };
+
+        let src : [usize; 2];
+        let dest : [usize; 2];
+        let mut promotion : Option<char> = None;
+        if input == "O-O" {
+            src = [back_rank, 5];
+            dest = [back_rank, 7];
+        } else if input == "O-O-O" {
+            src = [back_rank, 5];
+            dest = [back_rank, 3];
+        } else {
+            if !input.is_ascii() || (input.len() != 4 && input.len() != 5) {
+                return None;
+            }
+            src = checked_coord_to_rank_file(&input[0..2])?;
+            dest = checked_coord_to_rank_file(&input[2..4])?;
+            promotion = input.chars().nth(4);
+        }
+
+        let piece = self.get_piece_on_square(src);
+        if piece == '-' || piece.is_uppercase() != self.is_white_to_move {
+            return None;
+        }
+
+        let chess_move = ChessMove::new(&self, src, dest);
+        if (input == "O-O" && chess_move.move_type != MoveType::CastleKingSide) ||
+           (input == "O-O-O" && chess_move.move_type != MoveType::CastleQueenSide) {
+            return None;
+        }
+
+        // Pawns reaching the last rank must specify a promotion
+        let is_promotion = piece.to_ascii_uppercase() == 'P' && (dest[0] == 1 || dest[0] == 8);
+        return match (is_promotion, promotion) {
+            (false, None) => Some(chess_move),
+            (true, Some('q')) => Some(ChessMove::new_promotion(&self, src, dest, 1)),
+            (true, Some('r')) => Some(ChessMove::new_promotion(&self, src, dest, 2)),
+            (true, Some('b')) => Some(ChessMove::new_promotion(&self, src, dest, 3)),
+            (true, Some('n')) => Some(ChessMove::new_promotion(&self, src, dest, 4)),
+            _ => None,
+        };
+    }
+
+    /// Returns the moves played since the position was set as PGN movetext, 
+    /// such as "1. e4 e5 2. Nf3 *", ending with the result of the game.
+    pub fn to_pgn(&self) -> String {
+        let mut board = Board::new();
+        board.set_board_from_fen_string(&self.board_history.start_position);
+
+        let past_moves = self.board_history.past_moves();
+        let mut movetext : Vec<String> = vec![];
+        let mut move_number = board.fullmove_number;
+        if !board.is_white_to_move && !past_moves.is_empty() {
+            movetext.push(format!("{}...", move_number));
+        }
+
+        for chess_move in &past_moves {
+            if board.is_white_to_move {
+                movetext.push(format!("{}.", move_number));
+            } else {
+                move_number += 1;
+            }
+            movetext.push(board.move_to_san(chess_move));
+            board.make_move(*chess_move);
+        }
+
+        if self.is_checkmate() {
+            if self.is_white_to_move {
+                movetext.push(String::from("0-1"));
+            } else {
+                movetext.push(String::from("1-0"));
+            }
+        } else if self.is_draw() {
+            movetext.push(String::from("1/2-1/2"));
+        } else {
+            movetext.push(String::from("*"));
+        }
+
+        return movetext.join(" ");
+    }
+
+    /// Returns the piece on the squar, specified by the square index
+    pub fn get_piece_by_square_index(&self, square_inx : usize) -> char {
+        assert!(square_inx < 64);
+        return self.squares[square_inx];
+    }
+    
+    /// Returns the piece on the square specified by a rank and file. 
+    /// Off-board squares are reported as empty ('-').
+    pub fn get_piece_on_square(&self, rank_file: [usize; 2]) -> char {
+        match self.square_index(rank_file) {
+            Some(square_inx) => return self.squares[square_inx],
+            None => return '-',
+        }
+    }
+
+    /// Render the board to the console. Only used when running the tests.
+    pub fn render(&self) {
+        eprint!("{}", self.to_display_string(true));
+    }
+
+    /// Returns the board as text, one rank per line, with the rank numbers
+    /// down the side and the file letters along the bottom. From black's
+    /// perspective the board is rotated, so h1 is in the top left.
+    pub fn to_display_string(&self, from_white_perspective: bool) -> String {
+        let ranks : Vec<usize>;
+        let files : Vec<usize>;
+        if from_white_perspective {
+            ranks = (1..=8).rev().collect();
+            files = (1..=8).collect();
+        } else {
+            ranks = (1..=8).collect();
+            files = (1..=8).rev().collect();
+        }
+
+        let mut display_string = String::new();
+        for rank in &ranks {
+            display_string.push_str(&format!("{} ", rank));
+            for file in &files {
+                display_string.push_str(&format!(" {} ", self.get_piece_on_square([*rank, *file])));
+            }
+            display_string.push('\n');
+        }
+
+        display_string.push_str("  ");
+        for file in &files {
+            let file_alpha = (b'a' + (*file - 1) as u8) as char;
+            display_string.push_str(&format!(" {} ", file_alpha));
+        }
+        display_string.push('\n');
+
+        return display_string;
+    }
+
+    pub fn white_to_move(&self) -> bool {
+        return self.is_white_to_move;
+    }
+
+    /// The number of halfmoves since the last capture or pawn move, used
+    /// for the fifty move rule.
+    pub fn halfmove_clock(&self) -> u32 {
+        return self.halfmove_clock;
+    }
+
+    /// The number of the current move. Starts at 1 and increases after
+    /// each of black's moves.
+    pub fn fullmove_number(&self) -> u32 {
+        return self.fullmove_number;
+    }
+
+    pub fn set_is_white_to_move(&mut self, is_white_to_move: bool) {
+        self.is_white_to_move = is_white_to_move;
+    }
+
+    /// Methods for checking if a square is free
+    pub fn is_occupied(&self, rank_file: [usize; 2]) -> bool {
+        let piece = self.get_piece_on_square(rank_file);
+        return piece != '-';
+    }
+
+    pub fn is_occupied_by_white(&self, rank_file: [usize; 2]) -> bool {
+        if !self.is_occupied(rank_file) {
+            return false;
+        }
+        let is_white = self.get_piece_on_square(rank_file).is_uppercase();
+        return is_white;
+    }
+
+    pub fn is_occupied_by_black(&self, rank_file: [usize; 2]) -> bool {
+        if !self.is_occupied(rank_file) {
+            return false;
+        }
+        let is_black = !self.get_piece_on_square(rank_file).is_uppercase();
+        return is_black;
+    }
+
+    pub fn is_occupied_by_king(&self, rank_file: [usize; 2]) -> bool {
+        let piece = self.get_piece_on_square(rank_file);
+        return piece == 'k' || piece == 'K';
+    }
+
+    pub fn get_king_rank_file(&self) -> [usize; 2] {
+
+        if self.is_white_to_move {
+            return self.white_king_rank_file;
+        } 
+
+        return self.black_king_rank_file;
+    }
+
+    pub fn is_valid_rank_file(&self, rank_file: [usize; 2]) -> bool {
+        return !(rank_file[0] > 8 || rank_file[0] < 1 || rank_file[1] > 8 || rank_file[1] < 1);
+    }
+
+    /// Change the value of a square without making a move.
+    /// Off-board squares are ignored.
+    pub fn clear_square(&mut self, rank_file: [usize; 2]) {
+        if let Some(square_inx) = self.square_index(rank_file) {
+            self.squares[square_inx] = '-';
+        }
+    }
+
+    /// Returns all the squares occupied by pieces of the specified
+    /// colour.
+    pub fn all_occupied_squares(&self, find_occupied_by_white: bool) -> Vec<[usize; 2]> {
+        let mut occupied_squares : Vec<[usize; 2]> = vec![];
+        for rank in (1 as usize)..(9 as usize) {
+            for file in (1 as usize)..(9 as usize) {
+                if ( find_occupied_by_white && self.is_occupied_by_white([rank, file]) ) ||
+                   (!find_occupied_by_white && self.is_occupied_by_black([rank, file]) ) {
+                    occupied_squares.push([rank, file]);
+                }
+            }
+        }
+
+        return occupied_squares;
+    }
+
+    pub fn clear_history(&mut self) {
         self.board_history.clear();
     }
 
     /// Moves the piece from src to dest, and leaves the src square empty
     fn move_piece(&mut self, src: [usize ; 2], dest: [usize; 2]) {
-        let dest_index = self.square_index(dest);
-        let src_index = self.square_index(src);
+        let dest_index = self.on_board_square_index(dest);
+        let src_index = self.on_board_square_index(src);
         self.squares[dest_index] = self.squares[src_index];
         self.squares[src_index] = '-';
 
@@ -474,15 +1376,23 @@ impl Board {
     }
 
     /// Sets the piece at the square. By convention, uppercase is white,
-    /// lowercase is a black piece.
+    /// lowercase is a black piece. Off-board squares are ignored.
     fn set_piece(&mut self, piece: char, rank_file: [usize; 2]) {
+        let square_inx = match self.square_index(rank_file) {
+            Some(square_inx) => square_inx,
+            None => {
+                console_log!("Board::set_piece: Square {:?} is off the board", rank_file);
+                return;
+            }
+        };
+
         if piece == 'K' {
             self.white_king_rank_file = rank_file;
         } else if piece == 'k' {
             self.black_king_rank_file = rank_file;
         }
 
-        self.squares[self.square_index(rank_file)] = piece;
+        self.squares[square_inx] = piece;
     }
 
     /// Clears the board of all pieces. Resets en passant square
@@ -500,95 +1410,175 @@ impl Board {
 
     /// Convert the rank and file to the corresponding square index.
     /// Index from top left --> bottom right: a8, b8, c8 ... f1, g1, h1
-    fn square_index(&self, rank_file : [usize; 2]) -> usize {
-        // eprintln!("rank file = {:?}", rank_file);
-        if !(rank_file[0] > 0 && rank_file[0] < 9 && 
-            rank_file[1] > 0 && rank_file[1] < 9) {
-            panic!("rank_file = {:?}", rank_file);
+    /// Returns None if the square is off the board.
+    fn square_index(&self, rank_file : [usize; 2]) -> Option<usize> {
+        if !self.is_valid_rank_file(rank_file) {
+            return None;
         }
+        return Some(self.on_board_square_index(rank_file));
+    }
+
+    /// Same as square_index, for squares that are already known to be on the
+    /// board. Only checked in debug builds.
+    fn on_board_square_index(&self, rank_file : [usize; 2]) -> usize {
+        debug_assert!(self.is_valid_rank_file(rank_file), "rank_file = {:?}", rank_file);
         return (8 - 1 - (rank_file[0]-1))*8 + (rank_file[1]-1);
     }
 }
 
-/// Checks if a checkmating attack can be intercepted by another piece
-fn can_attack_be_intercepted(board : &Board, attacking_move : ChessMove) -> bool {
-    let piece_type = attacking_move.piece.to_ascii_uppercase();
-    let is_white = attacking_move.piece.is_uppercase();
-
-    // The attacked piece can not intercept the attack, so remove from the board when
-    // checking for intercepts
-    let mut board_copy = board.clone();
-    board_copy.clear_square(attacking_move.dest);
-
-    // Only moves from sliding pieces can be intercepted.
-    if piece_type != 'R' && piece_type != 'B' && piece_type != 'Q' {
-        return false;
+/// Converts a chess coordinate (such as e3) to a rank and file. Returns None
+/// if the coordinate is malformed or off the board. Only lowercase files are
+/// accepted.
+fn checked_coord_to_rank_file(coord: &str) -> Option<[usize; 2]> {
+    let bytes = coord.as_bytes();
+    if bytes.len() != 2 ||
+       !(b'a'..=b'h').contains(&bytes[0]) ||
+       !(b'1'..=b'8').contains(&bytes[1]) {
+        return None;
     }
 
-    console_log!("attacking piece = {}", attacking_move.piece);
+    return Some(coord_to_rank_file(coord));
+}
 
-    // todo, is there a way to reduce repetition with pieces::is_slide_clear_for_non_capture? 
-    let rank_dir = (attacking_move.dest[0] as i32 - attacking_move.src[0] as i32).signum();
-    let file_dir = (attacking_move.dest[1] as i32 - attacking_move.src[1] as i32).signum();
-    let mut traversed = [(attacking_move.src[0] as i32 + rank_dir) as usize,
-                        (attacking_move.src[1] as i32 + file_dir) as usize];
+/// Mirrors the rank of a square top to bottom. The invalid square [0, 0]
+/// stays invalid.
+fn mirrored_rank_file(rank_file: [usize; 2]) -> [usize; 2] {
+    if rank_file[0] < 1 || rank_file[0] > 8 {
+        return rank_file;
+    }
+    return [9 - rank_file[0], rank_file[1]];
+}
 
-    while traversed[0] != attacking_move.dest[0] || traversed[1] != attacking_move.dest[1] {
+/// Returns true if the square is reached from start by moving one or more
+/// steps in the direction, a change in rank and file of -1, 0 or 1.
+fn is_along_direction(start: [usize; 2], direction: [i32; 2], square: [usize; 2]) -> bool {
+    let offset = [square[0] as i32 - start[0] as i32, square[1] as i32 - start[1] as i32];
+    let distance = offset[0].abs().max(offset[1].abs());
 
-        if is_square_attacked(&board_copy, traversed, !is_white) {
-            // The sliding attack can be intercepted.
-            console_log!("traversed = {:?}", traversed);
-            return true;
-        }
+    return distance > 0 &&
+           offset[0] == direction[0] * distance &&
+           offset[1] == direction[1] * distance;
+}
 
-        traversed[0] = (traversed[0] as i32 + rank_dir) as usize;
-        traversed[1] = (traversed[1] as i32 + file_dir) as usize;
+/// Returns the square of the pawn captured by an en passant move. 
+fn en_passant_captured_pawn_sq(chess_move: &ChessMove) -> [usize; 2] {
+    if chess_move.piece == 'P' {
+        return [chess_move.dest[0] - 1, chess_move.dest[1]];
+    } else if chess_move.piece == 'p' {
+        return [chess_move.dest[0] + 1, chess_move.dest[1]];
     }
 
-    return false;
+    panic!("en passant move by piece = {}", chess_move.piece);
 }
 
 /// Tracks all the positions that have occured in the game. 
-/// Used to find when draw by three fold repeition occurs.
+/// Used to find when draw by three fold repeition occurs, and to unmake
+/// moves. Each move stores the zobrist hash of the position it led to, 
+/// rather than a copy of the board, and the number of times each hash
+/// has occured is counted so repetitions can be found without a search.
 #[derive(Clone, Debug)]
 struct BoardHistory {
-    past_positions : LinkedList<Board>,
+    start_position : String,
+    start_hash : u64,
+    entries : Vec<HistoryEntry>,
+    num_occurrences : HashMap<u64, u8>,
+}
 
+/// A move that has been made, and the position it led to.
+#[derive(Clone, Copy, Debug)]
+struct HistoryEntry {
+    hash : u64,
+    chess_move : ChessMove,
+    undo : MoveUndo,
 }
 
 impl BoardHistory {
     pub fn new() -> BoardHistory {
         return BoardHistory {
-            past_positions: LinkedList::new(),
+            start_position: String::new(),
+            start_hash: 0,
+            entries: vec![],
+            num_occurrences: HashMap::new(),
         };
     }
 
     pub fn clear(&mut self) {
-        self.past_positions.clear();
+        self.start_position.clear();
+        self.start_hash = 0;
+        self.entries.clear();
+        self.num_occurrences.clear();
     }
 
-    pub fn has_threefold_repetition_occurred(&self) -> bool {
-        if self.past_positions.len() < 3 {
-            return false;
+    /// Sets the position the moves are played from, given by its fen 
+    /// string and zobrist hash. Should be called after clear.
+    pub fn set_start_position(&mut self, fen_string: String, hash: u64) {
+        self.start_position = fen_string;
+        self.start_hash = hash;
+        self.add_occurrence(hash);
+    }
+
+    /// Records the move and the hash of the position it led to, along with 
+    /// the information needed to unmake it.
+    pub fn add_move(&mut self, hash: u64, chess_move: ChessMove, undo: MoveUndo) {
+        self.entries.push(HistoryEntry { hash, chess_move, undo });
+        self.add_occurrence(hash);
+    }
+
+    /// Removes the last move from the history, and returns the information
+    /// needed to restore the previous position.
+    pub fn remove_last_move(&mut self) -> Option<MoveUndo> {
+        let entry = self.entries.pop()?;
+        if let Some(count) = self.num_occurrences.get_mut(&entry.hash) {
+            *count -= 1;
+            if *count == 0 {
+                self.num_occurrences.remove(&entry.hash);
+            }
         }
-        let current_position = self.past_positions.back().unwrap();
-        let num_repetitions = self.past_positions.iter()
-            .filter(|&position| position.matches(&current_position)).count();
+        return Some(entry.undo);
+    }
+
+    pub fn has_threefold_repetition_occurred(&self) -> bool {
+        let current_position = match self.entries.last() {
+            Some(entry) => entry.hash,
+            None => self.start_hash,
+        };
+
+        return self.num_occurrences.get(&current_position).map_or(false, |&count| count >= 3);
+    }
 
-        return num_repetitions >= 3;
+    /// Returns the last move made, if any.
+    pub fn last_move(&self) -> Option<ChessMove> {
+        return self.entries.last().map(|entry| entry.chess_move);
     }
 
-    pub fn add_position(&mut self, mut board: Board) {
-        board.clear_history();
-        self.past_positions.push_back(board);
+    /// Returns the moves made since the start position, in order.
+    pub fn past_moves(&self) -> Vec<ChessMove> {
+        return self.entries.iter().map(|entry| entry.chess_move).collect();
     }
+
+    fn add_occurrence(&mut self, hash: u64) {
+        *self.num_occurrences.entry(hash).or_insert(0) += 1;
+    }
+}
+
+/// The state that is lost when a move is made, used to unmake the move.
+#[derive(Clone, Copy, Debug)]
+struct MoveUndo {
+    en_passant_sq : [usize; 2],
+    castle_king_side_white_avaliable : bool,
+    castle_king_side_black_avaliable : bool,
+    castle_queen_side_white_avaliable : bool,
+    castle_queen_side_black_avaliable : bool,
+    halfmove_clock : u32,
+    fullmove_number : u32,
 }
 
 #[cfg(test)]
 mod tests {
     use crate::console_log;
-    use crate::board::Board;
-    use crate::pieces::ChessMove;
+    use crate::board::{Board, GameOutcome, GameState};
+    use crate::pieces::{ChessMove, MoveType, is_square_attacked};
+    use crate::rules::pseudo_legal_moves_from_square;
 
     #[test]
     fn is_checkmate_test() {
@@ -615,13 +1605,79 @@ mod tests {
         board.set_is_white_to_move(false);
         assert!( !board.is_checkmate());
 
-        // Fix this edge case. King cannot capture attacking piece if it 
-        // involves theking moving into check
+        // King cannot capture attacking piece if it involves the king
+        // moving into check
         board.set_board_from_fen_string("8/8/8/8/8/3K4/3Q4/3k4");
         board.set_is_white_to_move(false);
         board.render();
         assert!( board.is_checkmate() ); 
     }
+
+    #[test]
+    fn is_checkmate_double_check() {
+        let mut board = Board::new();
+
+        // The queen can capture the knight, but the rook on h1 still gives check
+        board.set_board_from_fen_string("5qrk/5Np1/8/8/8/8/8/K6R b - - 0 1");
+        assert!( board.is_checkmate() );
+
+        // With only the knight giving check, capturing it escapes
+        board.set_board_from_fen_string("5qrk/5Np1/8/8/8/8/8/K7 b - - 0 1");
+        assert!( board.is_check() );
+        assert!( !board.is_checkmate() );
+    }
+
+    #[test]
+    fn set_board_from_fen_string_en_passant() {
+        let mut board = Board::new();
+        board.set_board_from_fen_string("rnbqkbnr/pp1ppppp/8/2p5/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 2");
+        assert_eq!(board.get_en_passant_square(), [3, 5]);
+        assert_eq!(board.halfmove_clock, 0);
+        assert_eq!(board.fullmove_number, 2);
+
+        board.set_board_from_fen_string("rnbqkbnr/pp1ppppp/8/2p5/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2");
+        assert_eq!(board.get_en_passant_square(), [0, 0]);
+        assert_eq!(board.halfmove_clock, 1);
+        assert_eq!(board.fullmove_number, 2);
+
+        // En passant squares on the wrong rank for the side to move are ignored
+        board.set_board_from_fen_string("rnbqkbnr/pp1ppppp/8/2p5/4P3/8/PPPP1PPP/RNBQKBNR w KQkq e3 0 2");
+        assert_eq!(board.get_en_passant_square(), [0, 0]);
+        board.set_board_from_fen_string("rnbqkbnr/pp1ppppp/8/2p5/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e4 0 2");
+        assert_eq!(board.get_en_passant_square(), [0, 0]);
+
+        // Uppercase files are not valid en passant squares
+        board.set_board_from_fen_string("rnbqkbnr/pp1ppppp/8/2p5/4P3/8/PPPP1PPP/RNBQKBNR b KQkq E3 0 2");
+        assert_eq!(board.get_en_passant_square(), [0, 0]);
+
+        // Missing move clocks use the defaults
+        board.set_board_from_fen_string("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq");
+        assert_eq!(board.get_en_passant_square(), [0, 0]);
+        assert_eq!(board.halfmove_clock, 0);
+        assert_eq!(board.fullmove_number, 1);
+    }
+
+    #[test]
+    fn to_fen_round_trip() {
+        let mut board = Board::new();
+        assert_eq!(board.to_fen(), "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+
+        let fen_strings = vec![
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "rnbqkbnr/pp1ppppp/8/2p5/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 2",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w Kq - 3 17",
+            "8/8/p7/P7/5k2/6q1/8/7K b - - 12 60",
+        ];
+
+        for fen_string in fen_strings {
+            board.set_board_from_fen_string(fen_string);
+            let first_fen = board.to_fen();
+            assert_eq!(first_fen, fen_string);
+
+            board.set_board_from_fen_string(&first_fen);
+            assert_eq!(board.to_fen(), first_fen);
+        }
+    }
     
     #[test]
     fn is_stalemate() {
@@ -634,6 +1690,410 @@ mod tests {
         assert!( !board.is_draw() );
     }
 
+    #[test]
+    fn is_draw_by_fifty_move_rule() {
+        let mut board = Board::new();
+        board.set_board_from_fen_string("1n5k/8/8/8/8/8/8/1N5K w - - 0 1");
+
+        // The knights move around cycles of different lengths so that no 
+        // position is repeated three times.
+        let white_cycle = [[1, 2], [2, 4], [4, 5], [3, 3], [2, 5], [4, 4], [3, 2], [1, 1], [2, 3], [3, 1]];
+        let black_cycle = [[8, 2], [7, 4], [6, 2], [8, 3], [7, 5], [6, 3]];
+
+        for i in 0..50 {
+            let white_move = ChessMove::new(&board, white_cycle[i % 10], white_cycle[(i + 1) % 10]);
+            board.make_move(white_move);
+            assert!( !board.is_draw() );
+
+            let black_move = ChessMove::new(&board, black_cycle[i % 6], black_cycle[(i + 1) % 6]);
+            board.make_move(black_move);
+            if i < 49 {
+                assert!( !board.is_draw() );
+            }
+        }
+
+        assert_eq!(board.halfmove_clock, 100);
+        assert!( board.is_draw() );
+
+        // A pawn move resets the clock
+        board.set_board_from_fen_string("7k/8/8/8/8/8/4P3/7K w - - 99 80");
+        board.make_move(ChessMove::new(&board, [2, 5], [3, 5]));
+        assert_eq!(board.halfmove_clock, 0);
+        assert!( !board.is_draw() );
+
+        // A capture resets the clock
+        board.set_board_from_fen_string("7k/p7/8/8/8/8/4n3/3N3K w - - 99 80");
+        board.make_move(ChessMove::new(&board, [1, 4], [2, 5]));
+        assert_eq!(board.halfmove_clock, 0);
+        assert!( !board.is_draw() );
+    }
+
+    #[test]
+    fn is_draw_by_insufficient_material() {
+        let mut board = Board::new();
+        assert!( !board.has_insufficient_material() );
+
+        // King vs king
+        board.set_board_from_fen_string("8/8/4k3/8/8/3K4/8/8 w - - 0 1");
+        assert!( board.has_insufficient_material() );
+        assert!( board.is_draw() );
+
+        // King and bishop vs king
+        board.set_board_from_fen_string("8/8/4k3/8/8/3KB3/8/8 w - - 0 1");
+        assert!( board.has_insufficient_material() );
+        assert!( board.is_draw() );
+
+        // King and knight vs king
+        board.set_board_from_fen_string("8/8/4k3/8/8/3K4/8/6n1 w - - 0 1");
+        assert!( board.has_insufficient_material() );
+        assert!( board.is_draw() );
+
+        // Bishops on the same colour square
+        board.set_board_from_fen_string("8/8/4k3/2b5/8/3KB3/8/8 w - - 0 1");
+        assert!( board.has_insufficient_material() );
+        assert!( board.is_draw() );
+
+        // Bishops on different colour squares
+        board.set_board_from_fen_string("8/8/4k3/1b6/8/3KB3/8/8 w - - 0 1");
+        assert!( !board.has_insufficient_material() );
+
+        // Two knights vs king is not automatically drawn
+        board.set_board_from_fen_string("8/8/4k3/8/8/3K4/8/5NN1 w - - 0 1");
+        assert!( !board.has_insufficient_material() );
+
+        // Any pawn, rook or queen can still checkmate
+        board.set_board_from_fen_string("8/8/4k3/8/8/3K4/7P/8 w - - 0 1");
+        assert!( !board.has_insufficient_material() );
+        board.set_board_from_fen_string("8/8/4k3/8/8/3K4/8/7r w - - 0 1");
+        assert!( !board.has_insufficient_material() );
+        board.set_board_from_fen_string("8/8/4k3/8/8/3K4/8/7Q w - - 0 1");
+        assert!( !board.has_insufficient_material() );
+    }
+
+    #[test]
+    fn unmake_move() {
+        let mut board = Board::new();
+        board.set_board_from_fen_string("r3k2r/1P6/8/3pP3/8/8/8/R3K2R w KQkq d6 0 1");
+        let start_position = board.clone();
+
+        let squares = vec![
+            ([5, 5], [6, 4]), // en passant
+            ([8, 5], [8, 7]), // castle king side
+            ([1, 5], [1, 3]), // castle queen side
+            ([8, 7], [8, 8]),
+        ];
+
+        let mut chess_moves = vec![];
+        let mut captured_pieces = vec![];
+        for (src, dest) in squares {
+            let chess_move = ChessMove::new(&board, src, dest);
+            chess_moves.push(chess_move);
+            captured_pieces.push(board.make_move(chess_move));
+        }
+
+        // capture and promote
+        let promotion = ChessMove::new_promotion(&board, [7, 2], [8, 1], 1);
+        chess_moves.push(promotion);
+        captured_pieces.push(board.make_move(promotion));
+
+        assert_eq!(captured_pieces, vec!['p', '-', '-', '-', 'r']);
+        assert_eq!(board.to_fen(), "Q4r1k/8/3P4/8/8/8/8/2KR3R b - - 0 3");
+
+        for i in (0..chess_moves.len()).rev() {
+            board.unmake_move(chess_moves[i], captured_pieces[i]);
+        }
+
+        assert!( board.matches(&start_position) );
+        assert_eq!( board.to_fen(), start_position.to_fen() );
+        assert_eq!( board.get_king_rank_file(), [1, 5] );
+    }
+
+    #[test]
+    fn unmake_refused_move() {
+        let mut board = Board::new();
+        board.make_move(ChessMove::new(&board, [2, 5], [4, 5]));
+        let fen_after_e4 = board.to_fen();
+
+        // White can not move twice, so d2d4 is refused and can not be unmade
+        let refused_move = ChessMove::new(&board, [2, 4], [4, 4]);
+        let captured_piece = board.make_move(refused_move);
+        board.unmake_move(refused_move, captured_piece);
+        assert_eq!(board.to_fen(), fen_after_e4);
+        assert_eq!(board.board_history.past_moves().len(), 1);
+    }
+
+    #[test]
+    fn capture_rook_removes_castle_rights() {
+        let mut board = Board::new();
+        board.set_board_from_fen_string("r3k2r/8/8/8/8/8/6B1/R3K2R w KQkq - 0 1");
+
+        // Capture the h8 rook while the a1 rook is still on its home square
+        board.make_move(ChessMove::new(&board, [2, 7], [8, 8]));
+        assert!( !board.is_castle_king_side_avaliable(false) );
+        assert!( board.is_castle_queen_side_avaliable(false) );
+        assert!( board.is_castle_king_side_avaliable(true) );
+        assert!( board.is_castle_queen_side_avaliable(true) );
+
+        // Castling queen side only removes the castling player's rights
+        board.set_board_from_fen_string("r3k2r/8/8/8/8/8/8/R3K2R b KQkq - 0 1");
+        board.make_move(ChessMove::new(&board, [8, 5], [8, 3]));
+        assert!( !board.is_castle_king_side_avaliable(false) );
+        assert!( !board.is_castle_queen_side_avaliable(false) );
+        assert!( board.is_castle_king_side_avaliable(true) );
+        assert!( board.is_castle_queen_side_avaliable(true) );
+    }
+
+    #[test]
+    fn perft_start_position() {
+        let mut board = Board::new();
+        assert_eq!(board.perft(0), 1);
+        assert_eq!(board.perft(1), 20);
+        assert_eq!(board.perft(2), 400);
+        assert_eq!(board.perft(3), 8902);
+        assert_eq!(board.perft(4), 197281);
+        assert_eq!(board.to_fen(), Board::new().to_fen());
+    }
+
+    #[test]
+    fn perft_kiwipete_and_position_5() {
+        // Positions 2 and 5 from https://www.chessprogramming.org/Perft_Results
+        let fen_strings_and_leaves = [
+            ("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1", 97862),
+            ("rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8", 62379),
+        ];
+
+        let mut board = Board::new();
+        for (fen_string, num_leaves) in fen_strings_and_leaves {
+            board.set_board_from_fen_string(fen_string);
+            let fen_before = board.to_fen();
+            assert_eq!(board.perft(3), num_leaves, "{}", fen_string);
+
+            // Every move made by perft is unmade
+            assert_eq!(board.to_fen(), fen_before);
+        }
+    }
+
+    #[test]
+    fn move_to_san() {
+        let mut board = Board::new();
+        assert_eq!(board.move_to_san(&ChessMove::new(&board, [1, 7], [3, 6])), "Nf3");
+
+        // Pawn capture
+        board.set_board_from_fen_string("rnbqkbnr/ppp1pppp/8/3p4/4P3/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 2");
+        assert_eq!(board.move_to_san(&ChessMove::new(&board, [4, 5], [5, 4])), "exd5");
+
+        // Castle
+        board.set_board_from_fen_string("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1");
+        assert_eq!(board.move_to_san(&ChessMove::new(&board, [1, 5], [1, 7])), "O-O");
+        assert_eq!(board.move_to_san(&ChessMove::new(&board, [1, 5], [1, 3])), "O-O-O");
+
+        // Promotion
+        board.set_board_from_fen_string("8/4P3/8/8/8/k7/8/K7 w - - 0 1");
+        assert_eq!(board.move_to_san(&ChessMove::new_promotion(&board, [7, 5], [8, 5], 1)), "e8=Q");
+        assert_eq!(board.move_to_san(&ChessMove::new_promotion(&board, [7, 5], [8, 5], 4)), "e8=N");
+
+        // Knights on b1 and f3 can both move to d2
+        board.set_board_from_fen_string("4k3/8/8/8/8/5N2/8/1N2K3 w - - 0 1");
+        assert_eq!(board.move_to_san(&ChessMove::new(&board, [1, 2], [2, 4])), "Nbd2");
+
+        // Rooks on the same file
+        board.set_board_from_fen_string("4k3/8/8/R7/8/8/8/R3K3 w - - 0 1");
+        assert_eq!(board.move_to_san(&ChessMove::new(&board, [1, 1], [3, 1])), "R1a3");
+
+        // Check and checkmate
+        board.set_board_from_fen_string("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1");
+        assert_eq!(board.move_to_san(&ChessMove::new(&board, [1, 1], [8, 1])), "Ra8#");
+        board.set_board_from_fen_string("6k1/5pp1/8/8/8/8/8/R5K1 w - - 0 1");
+        assert_eq!(board.move_to_san(&ChessMove::new(&board, [1, 1], [8, 1])), "Ra8+");
+    }
+
+    #[test]
+    fn parse_move() {
+        let mut board = Board::new();
+        let chess_move = board.parse_move("g1f3").unwrap();
+        assert!( chess_move.is_the_same_as(&ChessMove::new(&board, [1, 7], [3, 6])) );
+        assert_eq!( chess_move.move_type, MoveType::Standard );
+
+        // Promotion
+        board.set_board_from_fen_string("8/4P3/8/8/8/k7/8/K7 w - - 0 1");
+        let chess_move = board.parse_move("e7e8q").unwrap();
+        assert_eq!( chess_move.move_type, MoveType::PromoteToQueen );
+        assert_eq!( board.parse_move("e7e8n").unwrap().move_type, MoveType::PromoteToKnight );
+        assert!( board.parse_move("e7e8").is_none() );
+
+        // Castles
+        board.set_board_from_fen_string("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1");
+        assert_eq!( board.parse_move("O-O").unwrap().move_type, MoveType::CastleKingSide );
+        assert_eq!( board.parse_move("O-O-O").unwrap().move_type, MoveType::CastleQueenSide );
+        board.set_board_from_fen_string("r3k2r/8/8/8/8/8/8/R3K2R b KQkq - 0 1");
+        let chess_move = board.parse_move("O-O").unwrap();
+        assert_eq!( chess_move.src, [8, 5] );
+        assert_eq!( chess_move.dest, [8, 7] );
+
+        // En passant
+        board.set_board_from_fen_string("rnbqkbnr/ppp1pppp/8/3pP3/8/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 3");
+        assert_eq!( board.parse_move("e5d6").unwrap().move_type, MoveType::EnPassant );
+
+        // Rejected inputs
+        board = Board::new();
+        assert!( board.parse_move("e7e5").is_none() );
+        assert!( board.parse_move("e3e4").is_none() );
+        assert!( board.parse_move("i2i4").is_none() );
+        assert!( board.parse_move("e0e4").is_none() );
+        assert!( board.parse_move("E2E4").is_none() );
+        assert!( board.parse_move("e2").is_none() );
+        assert!( board.parse_move("e2e4q").is_none() );
+    }
+
+    #[test]
+    fn legal_moves_from_pinned_piece() {
+        let mut board = Board::new();
+        board.set_board_from_fen_string("4r1k1/8/8/8/8/8/4B3/4K3 w - - 0 1");
+        assert!( pseudo_legal_moves_from_square(&board, [2, 5]).len() > 0 );
+        assert_eq!( board.legal_moves_from_square([2, 5]).len(), 0 );
+
+        // The only white piece that is not the king is pinned
+        board.set_board_from_fen_string("8/8/8/8/3b4/1p6/1Nk5/K7 w - - 0 1");
+        assert!( pseudo_legal_moves_from_square(&board, [2, 2]).len() > 0 );
+        assert_eq!( board.legal_moves_from_square([2, 2]).len(), 0 );
+        assert!( board.is_draw() );
+    }
+
+    #[test]
+    fn zobrist_hash() {
+        let mut board_1 = Board::new();
+        for chess_move in ["g1f3", "g8f6", "b1c3"].iter() {
+            board_1.make_move(board_1.parse_move(chess_move).unwrap());
+        }
+
+        let mut board_2 = Board::new();
+        for chess_move in ["b1c3", "g8f6", "g1f3"].iter() {
+            board_2.make_move(board_2.parse_move(chess_move).unwrap());
+        }
+
+        assert!( board_1.matches(&board_2) );
+        assert_eq!( board_1.zobrist_hash(), board_2.zobrist_hash() );
+
+        board_2.set_is_white_to_move(true);
+        assert_ne!( board_1.zobrist_hash(), board_2.zobrist_hash() );
+
+        // Castle rights and en passant squares change the hash
+        let mut board_3 = Board::new();
+        board_3.set_board_from_fen_string("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQk - 0 1");
+        assert_ne!( Board::new().zobrist_hash(), board_3.zobrist_hash() );
+
+        board_3.set_board_from_fen_string("rnbqkbnr/ppp1pppp/8/8/3pP3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 3");
+        let hash_with_en_passant = board_3.zobrist_hash();
+        board_3.set_board_from_fen_string("rnbqkbnr/ppp1pppp/8/8/3pP3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 3");
+        assert_ne!( hash_with_en_passant, board_3.zobrist_hash() );
+    }
+
+    #[test]
+    fn get_legal_moves() {
+        let mut board = Board::new();
+        assert_eq!( board.get_legal_moves().len(), 20 );
+
+        // Mate in one
+        board.set_board_from_fen_string("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1");
+        let mating_move = ChessMove::new(&board, [1, 1], [8, 1]);
+        assert!( board.get_legal_moves().iter().any(|chess_move| chess_move.is_the_same_as(&mating_move)) );
+        board.make_move(mating_move);
+        assert!( board.get_legal_moves().is_empty() );
+        assert!( board.is_checkmate() );
+        assert!( !board.is_draw() );
+    }
+
+    #[test]
+    fn is_draw_by_repetition_phantom_en_passant() {
+        let mut board = Board::new();
+
+        // 1. e4 leaves an en passant square that black can not capture on, so the
+        // position is repeated after 3. Ng1 and 5. Ng1
+        let chess_moves = ["e2e4", "g8f6", "g1f3", "f6g8", "f3g1"];
+        board.make_move(board.parse_move(chess_moves[0]).unwrap());
+        let first_occurrence = board.clone();
+        assert_eq!( board.get_en_passant_square(), [3, 5] );
+
+        for i in 1..5 {
+            board.make_move(board.parse_move(chess_moves[i]).unwrap());
+        }
+        assert_eq!( board.get_en_passant_square(), [0, 0] );
+        assert!( board.matches(&first_occurrence) );
+        assert!( !board.is_draw() );
+
+        for chess_move in ["g8f6", "g1f3", "f6g8", "f3g1"].iter() {
+            board.make_move(board.parse_move(chess_move).unwrap());
+        }
+        assert!( board.is_draw() );
+
+        // The en passant square matters when the capture can be played
+        board.set_board_from_fen_string("rnbqkbnr/ppp1pppp/8/8/3pP3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 3");
+        let with_en_passant = board.clone();
+        board.set_board_from_fen_string("rnbqkbnr/ppp1pppp/8/8/3pP3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 3");
+        assert!( !board.matches(&with_en_passant) );
+    }
+
+    #[test]
+    fn to_pgn() {
+        let mut board = Board::new();
+        assert_eq!( board.to_pgn(), "*" );
+
+        for chess_move in ["e2e4", "e7e5", "f1c4", "b8c6"].iter() {
+            board.make_move(board.parse_move(chess_move).unwrap());
+        }
+        assert_eq!( board.to_pgn(), "1. e4 e5 2. Bc4 Nc6 *" );
+
+        // Scholar's mate
+        for chess_move in ["d1h5", "g8f6", "h5f7"].iter() {
+            board.make_move(board.parse_move(chess_move).unwrap());
+        }
+        assert_eq!( board.to_pgn(), "1. e4 e5 2. Bc4 Nc6 3. Qh5 Nf6 4. Qxf7# 1-0" );
+
+        // Starting from a position with black to move
+        board.set_board_from_fen_string("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1");
+        for chess_move in ["c7c5", "g1f3"].iter() {
+            board.make_move(board.parse_move(chess_move).unwrap());
+        }
+        assert_eq!( board.to_pgn(), "1... c5 2. Nf3 *" );
+    }
+
+    #[test]
+    fn is_draw_by_repetition_long_game() {
+        let mut board = Board::new();
+        board.set_board_from_fen_string("1n5k/8/8/8/8/8/8/1N5K w - - 0 1");
+
+        // Shuffle through many positions without repeating any three times, then
+        // return to the start position twice.
+        let white_cycle = [[1, 2], [2, 4], [4, 5], [3, 3], [2, 5], [4, 4], [3, 2], [1, 1], [2, 3], [3, 1]];
+        let black_cycle = [[8, 2], [7, 4], [6, 2], [8, 3], [7, 5], [6, 3]];
+        let mut chess_moves : Vec<ChessMove> = vec![];
+        let mut captured_pieces : Vec<char> = vec![];
+        for i in 0..60 {
+            let white_move = ChessMove::new(&board, white_cycle[i % 10], white_cycle[(i + 1) % 10]);
+            captured_pieces.push(board.make_move(white_move));
+            chess_moves.push(white_move);
+
+            let black_move = ChessMove::new(&board, black_cycle[i % 6], black_cycle[(i + 1) % 6]);
+            captured_pieces.push(board.make_move(black_move));
+            chess_moves.push(black_move);
+
+            // The start position occurs after every 30 moves by each side
+            let num_occurrences = 1 + (i + 1) / 30;
+            assert_eq!( board.board_history.has_threefold_repetition_occurred(), 
+                        num_occurrences >= 3 && (i + 1) % 30 == 0 );
+        }
+        assert_eq!( board.board_history.entries.len(), 120 );
+        assert!( board.board_history.has_threefold_repetition_occurred() );
+
+        // Unmaking the moves removes them from the history
+        for i in (0..chess_moves.len()).rev() {
+            board.unmake_move(chess_moves[i], captured_pieces[i]);
+        }
+        assert!( board.board_history.entries.is_empty() );
+        assert!( !board.board_history.has_threefold_repetition_occurred() );
+        assert_eq!( board.to_fen(), "1n5k/8/8/8/8/8/8/1N5K w - - 0 1" );
+    }
+
     #[test]
     fn is_draw_by_repetition_1() {
         let mut board = Board::new();
@@ -730,4 +2190,369 @@ mod tests {
         board.render();
         assert!( board.is_draw());
     }
+
+    #[test]
+    fn off_board_squares() {
+        let mut board = Board::new();
+        let fen_before = board.to_fen();
+
+        assert_eq!(board.get_piece_on_square([0, 0]), '-');
+        assert_eq!(board.get_piece_on_square([9, 1]), '-');
+        assert_eq!(board.get_piece_on_square([1, 9]), '-');
+        assert!(board.legal_moves_from_square([9, 1]).is_empty());
+        board.clear_square([0, 5]);
+
+        // A move onto a square off the board is not made
+        let off_board_move = ChessMove {
+            src: [2, 5],
+            dest: [9, 5],
+            piece: 'P',
+            move_type: MoveType::Standard,
+        };
+        assert_eq!(board.make_move(off_board_move), '-');
+        assert_eq!(board.to_fen(), fen_before);
+
+        // A rank with too many files. The extra queen off the board is ignored
+        board.set_board_from_fen_string("rnbqkbnrq/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+        assert_eq!(board.to_fen(), fen_before);
+        assert_eq!(board.all_occupied_squares(false).len(), 16);
+    }
+
+    #[test]
+    fn is_legal_move() {
+        let mut board = Board::new();
+        let legal_move = ChessMove::new(&board, [2, 5], [4, 5]);
+        assert!(board.is_legal_move(&legal_move));
+
+        // Black's piece can not move on white's turn
+        let wrong_side_move = ChessMove::new(&board, [7, 5], [5, 5]);
+        assert!(!board.is_legal_move(&wrong_side_move));
+
+        // The knight on c3 is pinned to the king by the bishop on b4
+        board.set_board_from_fen_string("rnbqk1nr/pppp1ppp/4p3/8/1b1P4/2N5/PPP1PPPP/R1BQKBNR w KQkq - 2 3");
+        let pinned_move = ChessMove::new(&board, [3, 3], [5, 4]);
+        assert!(!board.is_legal_move(&pinned_move));
+
+        // Castling out of check from the rook on e8
+        board.set_board_from_fen_string("4r1k1/8/8/8/8/8/8/4K2R w K - 0 1");
+        let castle_move = ChessMove {
+            src: [1, 5],
+            dest: [1, 7],
+            piece: 'K',
+            move_type: MoveType::CastleKingSide,
+        };
+        assert!(!board.is_legal_move(&castle_move));
+
+        board.set_board_from_fen_string("6k1/8/8/8/8/8/8/4K2R w K - 0 1");
+        assert!(board.is_legal_move(&castle_move));
+    }
+
+    #[test]
+    fn pinned_pieces() {
+        let mut board = Board::new();
+
+        // Bishop pinned along the e-file can not move at all
+        board.set_board_from_fen_string("4r1k1/8/8/8/8/8/4B3/4K3 w - - 0 1");
+        assert_eq!(board.pinned_pieces(true), vec![([2, 5], [1, 0])]);
+        assert!(board.legal_moves_from_square([2, 5]).is_empty());
+
+        // Rook pinned along the e-file can still slide up to the pinning rook
+        board.set_board_from_fen_string("4r1k1/8/8/8/8/8/4R3/4K3 w - - 0 1");
+        assert_eq!(board.pinned_pieces(true), vec![([2, 5], [1, 0])]);
+        let rook_moves = board.legal_moves_from_square([2, 5]);
+        assert_eq!(rook_moves.len(), 6);
+        for rook_move in rook_moves {
+            assert_eq!(rook_move.dest[1], 5);
+        }
+
+        // Two pieces between the king and the rook, so neither is pinned
+        board.set_board_from_fen_string("4r1k1/8/8/8/4N3/8/4B3/4K3 w - - 0 1");
+        assert!(board.pinned_pieces(true).is_empty());
+
+        // A rook can not pin along a diagonal
+        board.set_board_from_fen_string("6k1/8/8/r7/8/8/3B4/4K3 w - - 0 1");
+        assert!(board.pinned_pieces(true).is_empty());
+
+        // Bishop pinned along a diagonal can move towards the pinning queen
+        board.set_board_from_fen_string("6k1/8/8/8/7q/8/5B2/4K3 w - - 0 1");
+        assert_eq!(board.pinned_pieces(true), vec![([2, 6], [1, 1])]);
+        assert_eq!(board.legal_moves_from_square([2, 6]).len(), 2);
+    }
+
+    #[test]
+    fn play_random_game() {
+        let max_moves = 300;
+        for seed in 0..8 {
+            let mut board = Board::new();
+            let outcome = board.play_random_game(seed, max_moves);
+
+            match outcome {
+                GameOutcome::Checkmate => {
+                    assert!(board.is_check() && board.get_legal_moves().is_empty());
+                },
+                GameOutcome::Draw => match board.game_state() {
+                    GameState::Stalemate => {
+                        assert!(!board.is_check() && board.get_legal_moves().is_empty());
+                    },
+                    GameState::DrawByRepetition => {
+                        assert!(board.board_history.has_threefold_repetition_occurred());
+                    },
+                    GameState::DrawByFiftyMove => assert!(board.halfmove_clock() >= 100),
+                    GameState::DrawByInsufficientMaterial => assert!(board.has_insufficient_material()),
+                    game_state => panic!("seed {} drawn in state {:?}", seed, game_state),
+                },
+                GameOutcome::MaxMovesReached => {
+                    assert!(!board.get_legal_moves().is_empty());
+                    assert_eq!(board.draw_by_rule(), None);
+                    assert_eq!(board.board_history.past_moves().len(), max_moves as usize);
+                },
+            }
+
+            // The same seed plays the same game
+            let mut replayed_board = Board::new();
+            assert_eq!(replayed_board.play_random_game(seed, max_moves), outcome);
+            assert_eq!(replayed_board.to_fen(), board.to_fen());
+        }
+    }
+
+    #[test]
+    fn game_state() {
+        let mut board = Board::new();
+        assert_eq!(board.game_state(), GameState::Ongoing);
+
+        // Midgame
+        board.set_board_from_fen_string("r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4");
+        assert_eq!(board.game_state(), GameState::Ongoing);
+
+        board.set_board_from_fen_string("rnbqkbnr/ppppp1pp/8/5p1Q/4P3/8/PPPP1PPP/RNB1KBNR b KQkq - 1 2");
+        assert_eq!(board.game_state(), GameState::Check);
+        assert!(board.is_check() && !board.is_checkmate() && !board.is_draw());
+
+        // Fool's mate
+        board.set_board_from_fen_string("rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3");
+        assert_eq!(board.game_state(), GameState::Checkmate);
+        assert!(board.is_checkmate() && !board.is_draw());
+
+        board.set_board_from_fen_string("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1");
+        assert_eq!(board.game_state(), GameState::Stalemate);
+        assert!(board.is_draw() && !board.is_checkmate());
+
+        board.set_board_from_fen_string("4k3/8/8/8/8/8/4P3/4K3 w - - 100 80");
+        assert_eq!(board.game_state(), GameState::DrawByFiftyMove);
+
+        board.set_board_from_fen_string("4k3/8/8/8/8/8/4N3/4K3 w - - 0 1");
+        assert_eq!(board.game_state(), GameState::DrawByInsufficientMaterial);
+
+        // Checkmate takes priority over the fifty move rule
+        board.set_board_from_fen_string("6k1/5ppp/8/8/8/8/8/R5K1 w - - 99 80");
+        board.make_move(ChessMove::new(&board, [1, 1], [8, 1]));
+        assert_eq!(board.game_state(), GameState::Checkmate);
+
+        // Knights moving back and forth repeat the starting position
+        board = Board::new();
+        for _ in 0..2 {
+            board.make_move(ChessMove::new(&board, [1, 7], [3, 6]));
+            board.make_move(ChessMove::new(&board, [8, 7], [6, 6]));
+            board.make_move(ChessMove::new(&board, [3, 6], [1, 7]));
+            board.make_move(ChessMove::new(&board, [6, 6], [8, 7]));
+        }
+        assert_eq!(board.game_state(), GameState::DrawByRepetition);
+        assert!(board.is_draw());
+    }
+
+    #[test]
+    fn to_display_string() {
+        let board = Board::new();
+
+        let white_view = board.to_display_string(true);
+        let white_lines : Vec<&str> = white_view.lines().collect();
+        assert_eq!(white_lines.len(), 9);
+        assert_eq!(white_lines[0], "8  r  n  b  q  k  b  n  r ");
+        assert_eq!(white_lines[7], "1  R  N  B  Q  K  B  N  R ");
+        assert_eq!(white_lines[8].trim(), "a  b  c  d  e  f  g  h");
+
+        let black_view = board.to_display_string(false);
+        let black_lines : Vec<&str> = black_view.lines().collect();
+        assert_eq!(black_lines.len(), 9);
+        assert_eq!(black_lines[0], "1  R  N  B  K  Q  B  N  R ");
+        assert_eq!(black_lines[7], "8  r  n  b  k  q  b  n  r ");
+        assert_eq!(black_lines[8].trim(), "h  g  f  e  d  c  b  a");
+    }
+
+    #[test]
+    fn capture_moves() {
+        let mut board = Board::new();
+        assert!(board.capture_moves().is_empty());
+
+        // The b7 pawn can capture on a8 or push to b8, and the e5 pawn can
+        // capture en passant on d6
+        board.set_board_from_fen_string("r3k3/1P6/8/3pP3/8/8/8/4K3 w - d6 0 1");
+        let capture_moves = board.capture_moves();
+        assert_eq!(capture_moves.len(), 5);
+
+        let mut num_promotions = 0;
+        let mut num_en_passant = 0;
+        for capture_move in capture_moves {
+            assert!(capture_move.dest == [8, 1] || capture_move.dest == [6, 4]);
+            match capture_move.move_type {
+                MoveType::EnPassant => num_en_passant += 1,
+                MoveType::Standard => panic!("quiet move {:?}", capture_move),
+                _ => num_promotions += 1,
+            }
+        }
+        assert_eq!(num_promotions, 4);
+        assert_eq!(num_en_passant, 1);
+
+        // Black to move only has the pawn capture
+        board.set_board_from_fen_string("4k3/8/8/3p4/4P3/8/8/4K3 b - - 0 1");
+        let capture_moves = board.capture_moves();
+        assert_eq!(capture_moves.len(), 1);
+        assert_eq!(capture_moves[0].dest, [4, 5]);
+    }
+
+    #[test]
+    fn mirrored() {
+        let board = Board::new();
+        let mirrored_board = board.mirrored();
+        assert!(!mirrored_board.white_to_move());
+        for rank in 1..=8 {
+            for file in 1..=8 {
+                let piece = board.get_piece_on_square([rank, file]);
+                let mirrored_piece = mirrored_board.get_piece_on_square([9 - rank, file]);
+                assert_eq!(piece.to_ascii_uppercase(), mirrored_piece.to_ascii_uppercase());
+                assert!(piece == '-' || piece.is_uppercase() != mirrored_piece.is_uppercase());
+            }
+        }
+
+        // Castle rights, kings and the en passant square are swapped
+        let mut board = Board::new();
+        board.set_board_from_fen_string("r3k2r/8/8/8/4Pp2/8/8/R3K3 b Qkq e3 0 1");
+        let mirrored_board = board.mirrored();
+        assert_eq!(mirrored_board.to_fen(), "r3k3/8/8/4pP2/8/8/8/R3K2R w KQq e6 0 1");
+        assert_eq!(mirrored_board.get_king_rank_file(), [1, 5]);
+        assert_eq!(mirrored_board.mirrored().to_fen(), board.to_fen());
+
+        // The evaluation is symmetric between the colours
+        let fen_strings = [
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1",
+            "r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3",
+        ];
+        for fen_string in fen_strings {
+            board.set_board_from_fen_string(fen_string);
+            assert_eq!(board.evaluate(), -board.mirrored().evaluate());
+        }
+    }
+
+    #[test]
+    fn fullmove_number() {
+        let mut board = Board::new();
+        assert_eq!(board.fullmove_number(), 1);
+        assert_eq!(board.halfmove_clock(), 0);
+
+        let moves = ["e2e4", "e7e5", "g1f3", "b8c6"];
+        for (i, input) in moves.iter().enumerate() {
+            let chess_move = board.parse_move(input).unwrap();
+            board.make_move(chess_move);
+            assert_eq!(board.fullmove_number(), 1 + (i as u32 + 1) / 2);
+        }
+        assert_eq!(board.fullmove_number(), 3);
+        assert_eq!(board.halfmove_clock(), 2);
+        assert_eq!(board.to_fen(), "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3");
+
+        // Unmaking black's move restores the fullmove number
+        let last_move = board.board_history.past_moves()[3];
+        board.unmake_move(last_move, '-');
+        assert_eq!(board.fullmove_number(), 2);
+        assert_eq!(board.halfmove_clock(), 1);
+
+        board.set_board_from_fen_string("rnbqkbnr/pp1ppppp/8/2p5/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2");
+        assert_eq!(board.fullmove_number(), 2);
+        assert_eq!(board.halfmove_clock(), 1);
+    }
+
+    #[test]
+    fn is_position_legal() {
+        let mut board = Board::new();
+        assert!(board.is_position_legal());
+
+        board.set_board_from_fen_string("r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4");
+        assert!(board.is_position_legal());
+
+        // Black is in check with white to move
+        board.set_board_from_fen_string("4k3/8/8/8/8/8/8/4RK2 w - - 0 1");
+        assert!(!board.is_position_legal());
+        board.set_is_white_to_move(false);
+        assert!(board.is_position_legal());
+
+        // Missing black king
+        board.set_board_from_fen_string("8/8/8/8/8/8/8/4K3 w - - 0 1");
+        assert!(!board.is_position_legal());
+
+        // Two white kings
+        board.set_board_from_fen_string("4k3/8/8/8/8/8/8/3KK3 w - - 0 1");
+        assert!(!board.is_position_legal());
+
+        // Pawn on the first rank
+        board.set_board_from_fen_string("4k3/8/8/8/8/8/8/P3K3 w - - 0 1");
+        assert!(!board.is_position_legal());
+    }
+
+    #[test]
+    fn attack_map() {
+        let fen_strings = [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "8/3r4/8/3q1P2/8/8/6np/5k1Q w - - 0 1",
+            "8/4p2p/4K3/8/2n5/1P6/6P1/8 w - - 0 1",
+        ];
+
+        let mut board = Board::new();
+        for fen_string in fen_strings {
+            board.set_board_from_fen_string(fen_string);
+            for by_white in [true, false] {
+                let attacked_squares = board.attack_map(by_white);
+                for rank in 1..=8 {
+                    for file in 1..=8 {
+                        let square_inx = (8 - rank) * 8 + (file - 1);
+                        assert_eq!(attacked_squares[square_inx],
+                                   is_square_attacked(&board, [rank, file], by_white),
+                                   "{} square {:?} by white {}", fen_string, [rank, file], by_white);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn make_moves() {
+        let mut board = Board::new();
+        let mut moves = vec![];
+        for input in ["e2e4", "d7d5", "e4d5", "d8d5"] {
+            let chess_move = board.parse_move(input).unwrap();
+            board.make_move(chess_move);
+            moves.push(chess_move);
+        }
+        let fen_after_moves = board.to_fen();
+
+        board = Board::new();
+        assert_eq!(board.make_moves(&moves), Ok(()));
+        assert_eq!(board.to_fen(), fen_after_moves);
+        assert_eq!(board.board_history.past_moves().len(), 4);
+
+        // The third move is the queen moving through its own pawn on d2
+        board = Board::new();
+        let start_position = board.clone();
+        let illegal_moves = [
+            ChessMove::new(&board, [2, 5], [4, 5]),
+            ChessMove::new(&board, [7, 5], [5, 5]),
+            ChessMove::new(&board, [1, 4], [3, 4]),
+            ChessMove::new(&board, [8, 7], [6, 6]),
+        ];
+        assert_eq!(board.make_moves(&illegal_moves), Err(2));
+        assert!(board.matches(&start_position));
+        assert_eq!(board.to_fen(), start_position.to_fen());
+        assert!(board.board_history.past_moves().is_empty());
+    }
 }
\ No newline at end of file
@@ -1,4 +1,4 @@
-use crate::board::Board;
+use crate::board::{Board, GameState};
 use crate::pieces;
 
 pub const CHECKMATE_VAL : f32 = 1000.0;
@@ -9,6 +9,84 @@ const BISHOP_VAL : f32 = 3.0;
 const KNIGHT_VAL : f32 = 3.0;
 const PAWN_VAL : f32 = 1.0;
 
+/// Centipawn values used by evaluate_centipawns
+pub const CHECKMATE_CENTIPAWNS : i32 = 100000;
+const QUEEN_CENTIPAWNS : i32 = 900;
+const ROOK_CENTIPAWNS : i32 = 500;
+const BISHOP_CENTIPAWNS : i32 = 320;
+const KNIGHT_CENTIPAWNS : i32 = 300;
+const PAWN_CENTIPAWNS : i32 = 100;
+
+/// Piece square tables, from white's perspective. Indexed by square
+/// index, from a8 --> h1. Black pieces use the vertically mirrored square.
+const PAWN_TABLE : [i32; 64] = [
+     0,  0,  0,  0,  0,  0,  0,  0,
+    50, 50, 50, 50, 50, 50, 50, 50,
+    10, 10, 20, 30, 30, 20, 10, 10,
+     5,  5, 10, 25, 25, 10,  5,  5,
+     0,  0,  0, 20, 20,  0,  0,  0,
+     5, -5,-10,  0,  0,-10, -5,  5,
+     5, 10, 10,-20,-20, 10, 10,  5,
+     0,  0,  0,  0,  0,  0,  0,  0,
+];
+
+const KNIGHT_TABLE : [i32; 64] = [
+   -50,-40,-30,-30,-30,-30,-40,-50,
+   -40,-20,  0,  0,  0,  0,-20,-40,
+   -30,  0, 10, 15, 15, 10,  0,-30,
+   -30,  5, 15, 20, 20, 15,  5,-30,
+   -30,  0, 15, 20, 20, 15,  0,-30,
+   -30,  5, 10, 15, 15, 10,  5,-30,
+   -40,-20,  0,  5,  5,  0,-20,-40,
+   -50,-40,-30,-30,-30,-30,-40,-50,
+];
+
+/// Evaluates the position in centipawns from white's perspective, using the
+/// material and piece square tables. Checkmates return +/- CHECKMATE_CENTIPAWNS
+/// and draws return 0.
+pub fn evaluate_centipawns(board : &Board) -> i32 {
+    match board.game_state() {
+        GameState::Checkmate => {
+            if board.white_to_move() {
+                return -CHECKMATE_CENTIPAWNS;
+            }
+            return CHECKMATE_CENTIPAWNS;
+        },
+        GameState::Stalemate |
+        GameState::DrawByRepetition |
+        GameState::DrawByFiftyMove |
+        GameState::DrawByInsufficientMaterial => return 0,
+        GameState::Ongoing | GameState::Check => {},
+    }
+
+    let mut score : i32 = 0;
+    for i in 0..64 {
+        let piece = board.get_piece_by_square_index(i);
+        if piece == '-' {
+            continue;
+        }
+
+        // Flip the rank for black pieces, so the tables can be shared
+        let table_inx = if piece.is_uppercase() { i } else { i ^ 56 };
+        let piece_score = match piece.to_ascii_uppercase() {
+            'P' => PAWN_CENTIPAWNS + PAWN_TABLE[table_inx],
+            'N' => KNIGHT_CENTIPAWNS + KNIGHT_TABLE[table_inx],
+            'B' => BISHOP_CENTIPAWNS,
+            'R' => ROOK_CENTIPAWNS,
+            'Q' => QUEEN_CENTIPAWNS,
+            _   => 0,
+        };
+
+        if piece.is_uppercase() {
+            score += piece_score;
+        } else {
+            score -= piece_score;
+        }
+    }
+
+    return score;
+}
+
 pub fn evaluate(board : &Board) -> f32 {
     let checks_score = evaluate_checks(&board);
     let material_score = evaluate_material(&board);
@@ -110,7 +188,7 @@ impl NumPiecesOnBoard {
 #[cfg(test)]
 mod tests {
     use crate::board::Board;
-    use crate::evaluate::{self, evaluate_material, evaluate};
+    use crate::evaluate::{self, evaluate_material, evaluate, CHECKMATE_CENTIPAWNS};
 
     #[test]
     fn evaluate_material_1() {
@@ -135,4 +213,30 @@ mod tests {
         board.set_board_from_fen_string("6k1/1R3ppp/8/8/8/7P/5PP1/2r3K1");
         assert_eq!(evaluate(&board), -evaluate::CHECK_VAL);
     }
+
+    #[test]
+    fn evaluate_centipawns() {
+        let mut board = Board::new();
+        assert_eq!(board.evaluate(), 0);
+
+        // White is up a rook
+        board.set_board_from_fen_string("1nbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQk - 0 1");
+        assert!(board.evaluate() >= 400);
+
+        // Black is up a rook
+        board.set_board_from_fen_string("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/1NBQKBNR w Kkq - 0 1");
+        assert!(board.evaluate() <= -400);
+
+        // Central pawns and knights are preferred
+        board.set_board_from_fen_string("rnbqkbnr/pppppppp/8/8/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2");
+        assert!(board.evaluate() > 0);
+
+        // White is checkmated
+        board.set_board_from_fen_string("8/6k1/8/8/8/8/5PPP/2r3K1 w - - 0 1");
+        assert_eq!(board.evaluate(), -CHECKMATE_CENTIPAWNS);
+        
+        // Draws evaluate to zero
+        board.set_board_from_fen_string("8/8/p7/P7/5k2/6q1/8/7K w - - 0 1");
+        assert_eq!(board.evaluate(), 0);
+    }
 }
@@ -11,12 +11,13 @@ mod players;
 mod search;
 mod utils;
 mod rules;
+mod zobrist;
 
 use board::Board;
 use players::{Player, HumanPlayer, ComputerPlayer};
 use utils::log;
 use pieces::ChessMove;
-use utils::coord_to_rank_file;
+use utils::{coord_to_rank_file, square_index_to_rank_file, rank_file_to_square_index};
 
 #[cfg(feature = "wee_alloc")]
 #[global_allocator]
@@ -150,4 +151,122 @@ impl GameState {
     pub fn is_white_to_move(&self) -> bool {
         return self.board.white_to_move();
     }
-}
\ No newline at end of file
+}
+
+/// WasmBoard. A thin wrapper around the board for a front end that drives
+/// the engine itself. Squares are indexed 0..64 from a8 through to h1, the
+/// same layout as get_current_position.
+#[wasm_bindgen]
+pub struct WasmBoard {
+    board : Board,
+}
+
+#[wasm_bindgen]
+impl WasmBoard {
+    pub fn new() -> WasmBoard {
+        return WasmBoard {
+            board: Board::new(),
+        };
+    }
+
+    pub fn set_fen(&mut self, fen: &str) {
+        self.board.set_board_from_fen_string(fen);
+    }
+
+    pub fn get_current_position(&self) -> Vec<u8> {
+        return self.board.get_current_position();
+    }
+
+    /// Makes a move given in long algebraic notation, such as e2e4, e7e8q
+    /// or O-O. Returns false, leaving the board unchanged, if the move is
+    /// malformed or not legal.
+    pub fn make_move_from_string(&mut self, mv: &str) -> bool {
+        let chess_move = match self.board.parse_move(mv) {
+            Some(chess_move) => chess_move,
+            None => return false,
+        };
+
+        if !self.board.is_legal_move(&chess_move) {
+            return false;
+        }
+
+        self.board.make_move(chess_move);
+        return true;
+    }
+
+    /// Returns the destination squares of the legal moves for the piece on
+    /// the square. Empty if the square is empty, off the board, or holds a
+    /// piece of the side not to move.
+    pub fn get_legal_moves_for_square(&self, sq: u8) -> Vec<u8> {
+        if sq >= 64 {
+            return vec![];
+        }
+
+        let rank_file = square_index_to_rank_file(sq as usize);
+        let piece = self.board.get_piece_on_square(rank_file);
+        if piece == '-' || piece.is_uppercase() != self.board.white_to_move() {
+            return vec![];
+        }
+
+        let mut dest_squares : Vec<u8> = vec![];
+        for chess_move in self.board.legal_moves_from_square(rank_file) {
+            let dest_inx = rank_file_to_square_index(chess_move.dest) as u8;
+            // Promotions give one move per piece to the same square
+            if !dest_squares.contains(&dest_inx) {
+                dest_squares.push(dest_inx);
+            }
+        }
+
+        return dest_squares;
+    }
+
+    pub fn is_checkmate(&self) -> bool {
+        return self.board.is_checkmate();
+    }
+
+    pub fn is_draw(&self) -> bool {
+        return self.board.is_draw();
+    }
+
+    pub fn white_to_move(&self) -> bool {
+        return self.board.white_to_move();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::WasmBoard;
+
+    #[test]
+    fn wasm_board_string_api() {
+        let mut wasm_board = WasmBoard::new();
+        assert!(wasm_board.white_to_move());
+
+        // e2 is index 52, e3 and e4 are 44 and 36
+        let mut e2_moves = wasm_board.get_legal_moves_for_square(52);
+        e2_moves.sort();
+        assert_eq!(e2_moves, vec![36, 44]);
+        assert!(wasm_board.get_legal_moves_for_square(12).is_empty());
+        assert!(wasm_board.get_legal_moves_for_square(64).is_empty());
+
+        assert!(wasm_board.make_move_from_string("e2e4"));
+        assert!(!wasm_board.white_to_move());
+        let position = wasm_board.get_current_position();
+        assert_eq!(position[52], 0);
+        assert_eq!(position[36], 2);
+
+        // Malformed and illegal moves leave the board unchanged
+        assert!(!wasm_board.make_move_from_string("e4e5"));
+        assert!(!wasm_board.make_move_from_string("e7e4"));
+        assert!(!wasm_board.make_move_from_string("xyz"));
+        assert!(!wasm_board.white_to_move());
+
+        // Fool's mate
+        assert!(wasm_board.make_move_from_string("e7e5"));
+        wasm_board.set_fen("rnbqkbnr/pppp1ppp/8/4p3/6P1/5P2/PPPPP2P/RNBQKBNR b KQkq - 0 2");
+        assert!(!wasm_board.is_checkmate());
+        assert!(wasm_board.make_move_from_string("d8h4"));
+        assert!(wasm_board.is_checkmate());
+        assert!(!wasm_board.is_draw());
+    }
+}
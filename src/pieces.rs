@@ -1,5 +1,5 @@
 use crate::console_log;
-use crate::utils::{log, coord_to_rank_file};
+use crate::utils::{log, coord_to_rank_file, rank_file_to_square_index};
 use crate::board::Board;
 use crate::rules::is_move_legal;
 
@@ -37,7 +37,7 @@ pub enum MoveType {
 }
 
 /// ChessMove, represents a move made by a player
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug)]
 pub struct ChessMove {
     pub src: [usize; 2],
     pub dest: [usize; 2],
@@ -92,7 +92,9 @@ impl ChessMove {
             }
         }
 
-        if (self.piece == 'p' || self.piece == 'P') && 
+        // Only a diagonal pawn move can capture en passant, a push onto the
+        // en passant square is a standard move
+        if (self.piece == 'p' || self.piece == 'P') && src[1] != dest[1] &&
             dest[0] == board.get_en_passant_square()[0] && 
             dest[1] == board.get_en_passant_square()[1] {
             self.move_type = MoveType::EnPassant;
@@ -302,8 +304,7 @@ fn pawn_capture_moves(board: &Board, src_rank_file: [usize; 2], is_white: bool)
         let dest_rank_file = capture_movement.dest_from_src(src_rank_file);
         if board.is_valid_rank_file(dest_rank_file) &&
            (is_capture(&board, dest_rank_file, is_white) || 
-            (dest_rank_file[0] == board.get_en_passant_square()[0] && 
-            dest_rank_file[1] == board.get_en_passant_square()[1])) {
+            is_en_passant_capture(&board, dest_rank_file, is_white)) {
 
             // Is this move a promotion?
             if ( is_white && dest_rank_file[0] == 8 ) ||
@@ -324,6 +325,25 @@ fn pawn_capture_moves(board: &Board, src_rank_file: [usize; 2], is_white: bool)
     return capture_moves;
 }
 
+/// Checks if a pawn capturing on the destination square is an en passant capture.
+/// The destination must be the current en passant square, which is only set 
+/// immediately after a double pawn push, and the pushed enemy pawn must be 
+/// behind it.
+fn is_en_passant_capture(board: &Board, dest_rank_file: [usize; 2], is_white: bool) -> bool {
+    if dest_rank_file != board.get_en_passant_square() {
+        return false;
+    }
+
+    let enemy_pawn = if is_white { 'p' } else { 'P' };
+    if is_white && dest_rank_file[0] == 6 {
+        return board.get_piece_on_square([5, dest_rank_file[1]]) == enemy_pawn;
+    } else if !is_white && dest_rank_file[0] == 3 {
+        return board.get_piece_on_square([4, dest_rank_file[1]]) == enemy_pawn;
+    }
+
+    return false;
+}
+
 /// Returns all possible non capture pawn moves from a given square. 
 fn pawn_non_capture_moves(board: &Board, src_rank_file: [usize; 2], is_white: bool) -> Vec<ChessMove> {
     let mut non_capture_moves : Vec<ChessMove> = vec![];
@@ -341,14 +361,22 @@ fn pawn_non_capture_moves(board: &Board, src_rank_file: [usize; 2], is_white: bo
         ];
     }
 
+    // Pawns can only move two squares from their starting rank
+    let is_on_starting_rank = ( is_white && src_rank_file[0] == 2 ) ||
+                              ( !is_white && src_rank_file[0] == 7 );
+
     for non_capture_movement in non_capture_movements {
         let dest_rank_file = non_capture_movement.dest_from_src(src_rank_file);
 
+        if non_capture_movement.delta_rank.abs() == 2 && !is_on_starting_rank {
+            continue;
+        }
+
         if !board.is_valid_rank_file(dest_rank_file) ||
            board.is_occupied(dest_rank_file) {
             continue;
         }
-        if is_slide_clear_for_non_capture(&board, src_rank_file, dest_rank_file, is_white, false) {
+        if is_slide_clear_for_non_capture(&board, src_rank_file, dest_rank_file) {
 
             // Is this move a promotion?
             if ( is_white && dest_rank_file[0] == 8 ) ||
@@ -504,42 +532,44 @@ pub fn king_standard_moves(board: &Board, src: [usize; 2], is_white: bool, move_
     return standard_moves;
 }
 
-/// Returns legal castle moves from the current position
+/// Returns legal castle moves from the current position. The king cannot castle 
+/// out of check, through an attacked square, or into check.
 pub fn king_castle_moves(board: &Board, src: [usize; 2], is_white: bool) -> Vec<ChessMove> {
     let mut possible_castle_moves : Vec<ChessMove> = vec![];
 
-    if is_square_attacked(&board, src, !is_white) {
+    let attacked_squares = board.attack_map(!is_white);
+    let is_attacked = |rank_file: [usize; 2]| attacked_squares[rank_file_to_square_index(rank_file)];
+    if is_attacked(src) {
         // no castle moves avaliable when king is in check
         return possible_castle_moves;        
     }
 
+    let back_rank = if is_white { 1 } else { 8 };
+    let rook = if is_white { 'R' } else { 'r' };
+
     // Check if the king 
-    if board.is_castle_king_side_avaliable(is_white) { 
-        let dest: [usize; 2];
-        if is_white {
-            dest = [1, 7];
-        } else {
-            dest = [8, 7];
-        }
+    if board.is_castle_king_side_avaliable(is_white) && 
+       board.get_piece_on_square([back_rank, 8]) == rook { 
+        let dest: [usize; 2] = [back_rank, 7];
         
-        let is_king = true;
-        if is_slide_clear_for_non_capture(&board, src, dest, is_white, is_king) {
+        // Checks the f and g file squares are empty and not attacked
+        if is_slide_clear_for_non_capture(&board, src, dest) &&
+           !board.is_occupied(dest) && !is_attacked([back_rank, 6]) && !is_attacked(dest) {
             let castle_move = ChessMove::new(&board, src, dest);
             possible_castle_moves.push(castle_move);
         }
 
     }
     
-    if board.is_castle_queen_side_avaliable(is_white) {
-        let dest: [usize; 2];
-        if is_white {
-            dest = [1, 3];
-        } else {
-            dest = [8, 3];
-        }
-
-        let is_king = true;
-        if is_slide_clear_for_non_capture(&board, src, dest, is_white, is_king) {
+    if board.is_castle_queen_side_avaliable(is_white) &&
+       board.get_piece_on_square([back_rank, 1]) == rook {
+        let dest: [usize; 2] = [back_rank, 3];
+
+        // Checks the d and c file squares are empty and not attacked. The king does
+        // not cross the b file square, so it only needs to be empty.
+        if is_slide_clear_for_non_capture(&board, src, dest) &&
+           !board.is_occupied(dest) && !is_attacked([back_rank, 4]) && !is_attacked(dest) &&
+           !board.is_occupied([back_rank, 2]) {
             let castle_move = ChessMove::new(&board, src, dest);
             possible_castle_moves.push(castle_move);
         }
@@ -570,9 +600,10 @@ fn is_capture_including_king_capture(board : &Board, dest_rank_file: [usize; 2],
 }
 
 /// Checks if a slide move is clear of other pieces. Slide moves
-/// handle movements for rooks, bishops, queens and king castles
-fn is_slide_clear_for_non_capture(board: &Board, src: [usize; 2], dest: [usize; 2], 
-    is_white: bool, is_king: bool) -> bool {
+/// handle movements for rooks, bishops, queens and king castles.
+/// The squares a castling king crosses are checked for attacks in
+/// king_castle_moves.
+fn is_slide_clear_for_non_capture(board: &Board, src: [usize; 2], dest: [usize; 2]) -> bool {
     let rank_dir = (dest[0] as i32 - src[0] as i32).signum();
     let file_dir = (dest[1] as i32 - src[1] as i32).signum();
     let mut traversed = [(src[0] as i32 + rank_dir) as usize,
@@ -584,11 +615,6 @@ fn is_slide_clear_for_non_capture(board: &Board, src: [usize; 2], dest: [usize;
             return false;
         }
 
-        // The king cannot enter check
-        if is_king && is_square_attacked(&board, traversed, !is_white) {
-            return false;
-        }
-
         // Check if we have traversed past the edge of the board
         if !board.is_valid_rank_file(traversed) {
             console_log!("[pieces::is_slide_clear]: ERROR! Tried to check a square off the edge of the board.");
@@ -599,19 +625,12 @@ fn is_slide_clear_for_non_capture(board: &Board, src: [usize; 2], dest: [usize;
         traversed[1] = (traversed[1] as i32 + file_dir) as usize;
     }
 
-
-    // Make sure the destination square doesn't put the king in check
-    if is_king && is_square_attacked(&board, dest, !is_white) {
-        return false;
-    }
-
-
     return true;                           
 }
 
 /// Checks that the slide move is clear without checking the
 /// destination square. 
-fn is_slide_clear_for_capture(board: &Board, src: [usize; 2], dest: [usize; 2], is_white: bool) -> bool {
+fn is_slide_clear_for_capture(board: &Board, src: [usize; 2], dest: [usize; 2]) -> bool {
     if src[0] == dest[0] && src[1] == dest[1] {
         return true;
     }
@@ -621,7 +640,7 @@ fn is_slide_clear_for_capture(board: &Board, src: [usize; 2], dest: [usize; 2],
     let new_dest = [(dest[0] as i32 - rank_dir) as usize,
                               (dest[1] as i32 - file_dir) as usize];
 
-    return is_slide_clear_for_non_capture(&board, src, new_dest, is_white, false);
+    return is_slide_clear_for_non_capture(&board, src, new_dest);
 }
 
 /// Returns true if a square is attacked by a piece of a specified colour.
@@ -679,7 +698,8 @@ pub fn pieces_attacking_square(board : &Board, rank_file : [usize; 2], is_attack
 mod tests {
     use crate::console_log;
     use crate::board::Board;
-    use crate::pieces::{self};
+    use crate::pieces::{self, ChessMove, MoveType};
+    use crate::rules::is_move_legal;
 
     #[test]
     fn is_slide_clear() {
@@ -691,17 +711,17 @@ mod tests {
         console_log!("set board");
         let src = [5 as usize, 4 as usize];
         let mut dest = [8 as usize, 4 as usize];
-        assert!( !pieces::is_slide_clear_for_non_capture(&board, src, dest, false, false) );
+        assert!( !pieces::is_slide_clear_for_non_capture(&board, src, dest) );
 
         dest = [5 as usize, 6 as usize];
-        assert!( pieces::is_slide_clear_for_capture(&board, src, dest, false) );
+        assert!( pieces::is_slide_clear_for_capture(&board, src, dest) );
     }
 
     #[test]
     fn possible_pawn_moves() {
         let mut board = Board::new();
         board.set_board_from_fen_string("8/4p2p/4K3/8/2n5/1P6/6P1/8 ");
-        let mut src = [5 as usize, 4 as usize];
+        let mut src = [3 as usize, 2 as usize];
         let mut is_white = true;
         assert_eq!( pieces::pawn_moves(&board, src, is_white).len(), 2);
 
@@ -810,4 +830,87 @@ mod tests {
         let is_white = true;
         assert_eq!( pieces::king_moves(&board, src, is_white).len(), 3);        
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn possible_castle_moves() {
+        let mut board = Board::new();
+        let src = [1 as usize, 5 as usize];
+        let is_white = true;
+
+        board.set_board_from_fen_string("4k3/8/8/8/8/8/8/R3K2R w KQ - 0 1");
+        assert_eq!( pieces::king_castle_moves(&board, src, is_white).len(), 2 );
+
+        // Can not castle while in check
+        board.set_board_from_fen_string("4k3/4r3/8/8/8/8/8/R3K2R w KQ - 0 1");
+        assert_eq!( pieces::king_castle_moves(&board, src, is_white).len(), 0 );
+
+        // Can not castle through the attacked f1 square
+        board.set_board_from_fen_string("4k3/5r2/8/8/8/8/8/R3K2R w KQ - 0 1");
+        let castle_moves = pieces::king_castle_moves(&board, src, is_white);
+        assert_eq!( castle_moves.len(), 1 );
+        assert_eq!( castle_moves[0].move_type, MoveType::CastleQueenSide );
+
+        // Can not castle through the attacked d1 square
+        board.set_board_from_fen_string("4k3/3r4/8/8/8/8/8/R3K2R w KQ - 0 1");
+        let castle_moves = pieces::king_castle_moves(&board, src, is_white);
+        assert_eq!( castle_moves.len(), 1 );
+        assert_eq!( castle_moves[0].move_type, MoveType::CastleKingSide );
+
+        // Can castle queen side when only the b1 square is attacked
+        board.set_board_from_fen_string("4k3/1r6/8/8/8/8/8/R3K2R w KQ - 0 1");
+        assert_eq!( pieces::king_castle_moves(&board, src, is_white).len(), 2 );
+
+        // Can not castle queen side when the b1 square is occupied
+        board.set_board_from_fen_string("4k3/8/8/8/8/8/8/RN2K2R w KQ - 0 1");
+        let castle_moves = pieces::king_castle_moves(&board, src, is_white);
+        assert_eq!( castle_moves.len(), 1 );
+        assert_eq!( castle_moves[0].move_type, MoveType::CastleKingSide );
+
+        // Can not castle onto an occupied c1 or g1 square
+        board.set_board_from_fen_string("4k3/8/8/8/8/8/8/R1B1K2R w KQ - 0 1");
+        let castle_moves = pieces::king_castle_moves(&board, src, is_white);
+        assert_eq!( castle_moves.len(), 1 );
+        assert_eq!( castle_moves[0].move_type, MoveType::CastleKingSide );
+
+        board.set_board_from_fen_string("4k3/8/8/8/8/8/8/R3K1NR w KQ - 0 1");
+        let castle_moves = pieces::king_castle_moves(&board, src, is_white);
+        assert_eq!( castle_moves.len(), 1 );
+        assert_eq!( castle_moves[0].move_type, MoveType::CastleQueenSide );
+    }
+
+    #[test]
+    fn possible_en_passant_moves() {
+        let mut board = Board::new();
+        board.set_board_from_fen_string("4k3/8/8/1Pp5/8/8/8/4K3 w - c6 0 2");
+        let en_passant_moves : Vec<_> = pieces::pawn_moves(&board, [5, 2], true).into_iter()
+            .filter(|chess_move| chess_move.move_type == MoveType::EnPassant).collect();
+        assert_eq!( en_passant_moves.len(), 1 );
+        assert_eq!( en_passant_moves[0].dest, [6, 3] );
+
+        // A stale en passant square is ignored when the pushed pawn is not there
+        board.set_board_from_fen_string("4k3/8/8/1P6/2p5/8/8/4K3 w - c6 0 2");
+        assert_eq!( pieces::pawn_moves(&board, [5, 2], true).len(), 1 );
+
+        // The pawns are pinned horizontally, capturing en passant exposes the king
+        board.set_board_from_fen_string("4k3/8/8/KPp4r/8/8/8/8 w - c6 0 2");
+        let en_passant_move = ChessMove::new(&board, [5, 2], [6, 3]);
+        assert_eq!( en_passant_move.move_type, MoveType::EnPassant );
+        assert!( pieces::pawn_moves(&board, [5, 2], true).iter()
+            .any(|chess_move| chess_move.is_the_same_as(&en_passant_move)) );
+        assert!( !board.legal_moves_from_square([5, 2]).iter()
+            .any(|chess_move| chess_move.is_the_same_as(&en_passant_move)) );
+        assert!( !is_move_legal(&board, &en_passant_move) );
+
+        // A pawn pushed onto the en passant square is not an en passant capture
+        board.set_board_from_fen_string("4k3/8/8/8/8/8/4P3/4K3 w - e3 0 1");
+        let fen_before = board.to_fen();
+        let push = ChessMove::new(&board, [2, 5], [3, 5]);
+        assert_eq!( push.move_type, MoveType::Standard );
+        assert!( board.get_legal_moves().iter()
+            .all(|chess_move| chess_move.move_type != MoveType::EnPassant) );
+        let captured_piece = board.make_move(push);
+        board.unmake_move(push, captured_piece);
+        assert_eq!( board.to_fen(), fen_before );
+        assert_eq!( board.get_piece_on_square([2, 5]), 'P' );
+    }
+}
@@ -28,19 +28,18 @@ pub fn all_possible_moves(board : &Board) -> Vec<ChessMove> {
         return vec![];
     }
 
-    let occupied_squares = board.all_occupied_squares(board.white_to_move());
-
-    let mut all_possible_moves : Vec<ChessMove> = vec![];
-    for square in occupied_squares {
-        all_possible_moves.append(&mut possible_moves_from_square(&board, square));
-    }
-
-    return all_possible_moves;
+    return board.get_legal_moves();
 }
 
 /// possible_moves_from_square: Given a chess board and a square, 
-/// generates all possible chess moves for the piece on that square.
+/// generates all legal chess moves for the piece on that square.
 pub fn possible_moves_from_square(board: &Board, rank_file: [usize; 2]) -> Vec<ChessMove> {
+    return board.legal_moves_from_square(rank_file);
+}
+
+/// pseudo_legal_moves_from_square: Generates the moves for the piece on 
+/// a square without checking if the move leaves the king in check.
+pub fn pseudo_legal_moves_from_square(board: &Board, rank_file: [usize; 2]) -> Vec<ChessMove> {
 
     let piece = board.get_piece_on_square(rank_file);
     let is_white = piece.is_uppercase();
@@ -54,20 +53,11 @@ pub fn possible_moves_from_square(board: &Board, rank_file: [usize; 2]) -> Vec<C
         'B' => moves = pieces::bishop_moves(&board, rank_file, is_white),
         'N' => moves = pieces::knight_moves(&board, rank_file, is_white),
         'P' => moves = pieces::pawn_moves(&board, rank_file, is_white),
+        '-' => moves = vec![],
         _ => panic!(),
     }
 
-    // Cull any moves that end in the king in check
-    let mut moves_without_check : Vec<ChessMove> = vec![];
-    for chess_move in moves {
-        let mut board_after_move = board.clone();
-        board_after_move.make_move(chess_move);
-        board_after_move.set_is_white_to_move(board.white_to_move());
-        if !board_after_move.is_check() {
-            moves_without_check.push(chess_move);
-        }
-    }
-    return moves_without_check;
+    return moves;
 } 
 
 /// Tests to see that the rules are working
@@ -1,5 +1,5 @@
 use crate::board::Board;
-use crate::evaluate::{evaluate, CHECKMATE_VAL};
+use crate::evaluate::{evaluate, CHECKMATE_VAL, CHECKMATE_CENTIPAWNS};
 use crate::rules::all_possible_moves;
 use crate::pieces::ChessMove;
 use crate::console_log;
@@ -94,25 +94,81 @@ pub fn alpha_beta_minimax(mut node: &mut Node, depth: usize,
     return minimax_eval;
 }
 
-/// Performance test move path optimization 
-/// https://www.chessprogramming.org/Perft
-pub fn perft(board: &Board, depth: u32) -> u32 {
+/// Returns the best move for the side to move, using a negamax search with
+/// alpha-beta pruning. Moves are made and unmade on a single copy of the board.
+/// Returns None if there are no legal moves.
+/// https://www.chessprogramming.org/Negamax
+pub fn negamax_best_move(board: &Board, depth: u32) -> Option<ChessMove> {
+    let mut board_copy = board.clone();
+    let mut alpha = -CHECKMATE_CENTIPAWNS - 1;
+    let beta = CHECKMATE_CENTIPAWNS + 1;
+
+    let mut best_move : Option<ChessMove> = None;
+    for chess_move in board.get_legal_moves() {
+        let captured_piece = board_copy.make_move(chess_move);
+        let eval = -negamax(&mut board_copy, depth.saturating_sub(1), -beta, -alpha, 1);
+        board_copy.unmake_move(chess_move, captured_piece);
+
+        if best_move.is_none() || eval > alpha {
+            alpha = eval;
+            best_move = Some(chess_move);
+        }
+    }
+
+    return best_move;
+}
+
+/// Returns the evaluation in centipawns from the perspective of the side to move.
+/// Checkmates found at a lower ply score higher, so the shortest mate is preferred.
+fn negamax(board: &mut Board, depth: u32, initial_alpha: i32, beta: i32, ply: i32) -> i32 {
+    let side_to_move = if board.white_to_move() { 1 } else { -1 };
 
     if depth == 0 {
-        return 1;
+        let eval = side_to_move * board.evaluate();
+        if eval == -CHECKMATE_CENTIPAWNS {
+            return eval + ply;
+        }
+        return eval;
     }
 
-    let mut total_leaves = 0;
-    let all_possible_moves = all_possible_moves(&board);
-    let num_possible_moves = all_possible_moves.len();
+    let legal_moves = board.get_legal_moves();
+    if legal_moves.is_empty() {
+        if board.is_check() {
+            return -CHECKMATE_CENTIPAWNS + ply;
+        }
+        return 0;
+    }
 
-    for i in 0..num_possible_moves {
-        let mut next_position = board.clone();
-        next_position.make_move(all_possible_moves[i]);
-        total_leaves += perft(&next_position, depth-1);
+    if board.draw_by_rule().is_some() {
+        return 0;
+    }
+
+    let mut alpha = initial_alpha;
+    let mut best_eval = -CHECKMATE_CENTIPAWNS - 1;
+    for chess_move in legal_moves {
+        let captured_piece = board.make_move(chess_move);
+        let eval = -negamax(board, depth - 1, -beta, -alpha, ply + 1);
+        board.unmake_move(chess_move, captured_piece);
+
+        if eval > best_eval {
+            best_eval = eval;
+        }
+        if eval > alpha {
+            alpha = eval;
+        }
+        if alpha >= beta {
+            break;
+        }
     }
 
-    return total_leaves;
+    return best_eval;
+}
+
+/// Performance test move path optimization 
+/// https://www.chessprogramming.org/Perft
+pub fn perft(board: &Board, depth: u32) -> u32 {
+    let mut board_copy = board.clone();
+    return board_copy.perft(depth) as u32;
 }
 
 pub fn count_leaves_in_tree(root : &Node, num_leaves : &mut u32, num_checks : &mut u32) {
@@ -203,13 +259,7 @@ mod tests {
         num_leaves = perft(&board, depth);
         assert_eq!(num_leaves, 400);
 
-        // depth = 3;
-        // num_leaves = perft(&board, depth);
-        // assert_eq!(num_leaves, 8902); // failing, getting 9194 positions
-
-        // depth = 4;
-        // num_leaves = perft(&board, depth);
-        // assert_eq!(num_leaves, 197281); // failing, getting 209691 positions
+        // Deeper perft results are tested by Board::perft in board.rs
     }
 
     #[test]
@@ -221,8 +271,39 @@ mod tests {
         let mut num_leaves = perft(&board, depth);
         assert_eq!( num_leaves, 48);
        
-        // depth = 2;
-        // num_leaves = perft(&board, depth);
-        // assert_eq!(num_leaves, 2039); // Fails, gets 2082 positions...
+        depth = 2;
+        num_leaves = perft(&board, depth);
+        assert_eq!(num_leaves, 2039);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn search_best_move_mate_in_one() {
+        let mut board = Board::new();
+        board.set_board_from_fen_string("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1");
+        let mating_move = ChessMove::new(&board, [1, 1], [8, 1]);
+        assert!( board.search_best_move(1).unwrap().is_the_same_as(&mating_move) );
+        assert!( board.search_best_move(2).unwrap().is_the_same_as(&mating_move) );
+
+        // Many moves lead to a forced mate, the quickest mate should be preferred
+        board.set_board_from_fen_string("6k1/5ppp/8/8/8/8/Q7/R5K1 w - - 0 1");
+        let selected_move = board.search_best_move(3).unwrap();
+        board.make_move(selected_move);
+        assert!( board.is_checkmate() );
+    }
+
+    #[test]
+    fn search_best_move_hanging_queen() {
+        let mut board = Board::new();
+        board.set_board_from_fen_string("5rk1/5p1p/6p1/1q6/8/7P/5PP1/1R3RK1 w - - 0 1");
+        let capture_queen = ChessMove::new(&board, [1, 2], [5, 2]);
+        assert!( board.search_best_move(1).unwrap().is_the_same_as(&capture_queen) );
+        assert!( board.search_best_move(2).unwrap().is_the_same_as(&capture_queen) );
+    }
+
+    #[test]
+    fn search_best_move_no_legal_moves() {
+        let mut board = Board::new();
+        board.set_board_from_fen_string("8/6k1/8/8/8/8/5PPP/2r3K1 w - - 0 1");
+        assert!( board.search_best_move(2).is_none() );
+    }
+}
@@ -30,4 +30,43 @@ pub fn coord_to_rank_file(coord : &str) -> [usize; 2] {
     let file = (file_alpha - b'a' + 1) as usize;
 
     return [rank, file];
-}
\ No newline at end of file
+}
+
+/// Converts a rank and file to a chess coordinate (such as e4, f3).
+/// The inverse of coord_to_rank_file.
+pub fn rank_file_to_coord(rank_file : [usize; 2]) -> String {
+    assert!(rank_file[0] >= 1 && rank_file[0] <= 8);
+    assert!(rank_file[1] >= 1 && rank_file[1] <= 8);
+
+    let file_alpha = (b'a' + (rank_file[1] - 1) as u8) as char;
+    let rank_digit = (b'0' + rank_file[0] as u8) as char;
+
+    return format!("{}{}", file_alpha, rank_digit);
+}
+
+
+/// Converts a square index, counted from a8 through to h1 as in
+/// Board::get_current_position, to a rank and file.
+pub fn square_index_to_rank_file(square_inx : usize) -> [usize; 2] {
+    assert!(square_inx < 64);
+    return [8 - square_inx / 8, square_inx % 8 + 1];
+}
+
+/// Converts a rank and file to a square index from a8 through to h1.
+/// The inverse of square_index_to_rank_file.
+pub fn rank_file_to_square_index(rank_file : [usize; 2]) -> usize {
+    assert!(rank_file[0] >= 1 && rank_file[0] <= 8);
+    assert!(rank_file[1] >= 1 && rank_file[1] <= 8);
+    return (8 - rank_file[0]) * 8 + (rank_file[1] - 1);
+}
+
+/// Advances the splitmix64 random number generator. Returns the new state
+/// and the next random number. A const fn so it can generate the zobrist
+/// keys at compile time.
+pub const fn splitmix64(state : u64) -> (u64, u64) {
+    let next_state = state.wrapping_add(0x9E3779B97F4A7C15);
+    let mut z = next_state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+    return (next_state, z ^ (z >> 31));
+}
@@ -0,0 +1,55 @@
+//! Random keys used to hash chess positions.
+//! See https://www.chessprogramming.org/Zobrist_Hashing
+
+use crate::utils::splitmix64;
+
+const PIECES : &str = "PNBRQKpnbrqk";
+const NUM_PIECE_SQUARE_KEYS : usize = 12 * 64;
+const BLACK_TO_MOVE_KEY_INX : usize = NUM_PIECE_SQUARE_KEYS;
+const CASTLE_KEYS_INX : usize = BLACK_TO_MOVE_KEY_INX + 1;
+const EN_PASSANT_KEYS_INX : usize = CASTLE_KEYS_INX + 4;
+const NUM_KEYS : usize = EN_PASSANT_KEYS_INX + 8;
+
+/// The keys are generated once at compile time from a fixed seed, so hashes
+/// are the same every time the engine runs.
+static KEYS : [u64; NUM_KEYS] = generate_keys();
+
+/// Returns the key for a piece on a square, where the square index
+/// matches Board::square_index.
+pub fn piece_square_key(piece: char, square_inx: usize) -> u64 {
+    assert!(square_inx < 64);
+    return match PIECES.find(piece) {
+        Some(piece_inx) => KEYS[piece_inx*64 + square_inx],
+        None => 0,
+    };
+}
+
+pub fn black_to_move_key() -> u64 {
+    return KEYS[BLACK_TO_MOVE_KEY_INX];
+}
+
+/// Keys for the castle rights, in the order K, Q, k, q.
+pub fn castle_key(castle_inx: usize) -> u64 {
+    assert!(castle_inx < 4);
+    return KEYS[CASTLE_KEYS_INX + castle_inx];
+}
+
+/// Returns the key for the file of an en passant square. Files start at 1.
+pub fn en_passant_file_key(file: usize) -> u64 {
+    assert!(file >= 1 && file <= 8);
+    return KEYS[EN_PASSANT_KEYS_INX + file - 1];
+}
+
+/// Fills the key table using the splitmix64 generator.
+const fn generate_keys() -> [u64; NUM_KEYS] {
+    let mut keys = [0 as u64; NUM_KEYS];
+    let mut state : u64 = 0x2545F4914F6CDD1D;
+    let mut i = 0;
+    while i < NUM_KEYS {
+        let (next_state, key) = splitmix64(state);
+        state = next_state;
+        keys[i] = key;
+        i += 1;
+    }
+    return keys;
+}